@@ -1,6 +1,6 @@
 //! This example requires feature **neust/webvpn**
 
-use neust::{auth::Credential, webvpn, Session};
+use neust::{auth::Credential, Session};
 use std::env;
 
 #[tokio::main]
@@ -24,14 +24,15 @@ async fn main() {
         panic!("error: {:?}", status)
     }
 
-    let client = session.client();
-    let request = client
-        .get(webvpn::encrypt_url(
-            "http://219.216.96.4/eams/teach/grade/course/person!search.action?semesterId=0",
-        ))
-        .build()
+    let body = session
+        .webvpn_client()
+        .get("http://219.216.96.4/eams/teach/grade/course/person!search.action?semesterId=0")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
         .unwrap();
-    let body = client.execute(request).await.unwrap().text().await.unwrap();
 
     let re = regex::Regex::new(r"<div>总平均绩点：([0-9.]+)</div>").unwrap();
     println!("{}", re.captures(&body).unwrap().get(1).unwrap().as_str());
@@ -19,7 +19,7 @@ async fn main() {
                     println!("{}", username);
                     break;
                 }
-                UserStatus::Rejected => continue,
+                UserStatus::Rejected { .. } => continue,
                 _ => {
                     panic!("something wrong: {:?}", status)
                 }
@@ -0,0 +1,188 @@
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+
+/// A captcha required by the CAS before login.
+///
+/// It's returned in [`RejectReason::CaptchaRequired`](crate::status::RejectReason::CaptchaRequired)
+/// when no [`CaptchaSolver`] answers it, see
+/// [`Credential::with_captcha_answer`](crate::auth::Credential::with_captcha_answer)
+/// to answer it in a follow-up login.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaptchaChallenge {
+    image: Vec<u8>,
+    content_type: Option<String>,
+}
+
+impl CaptchaChallenge {
+    pub(crate) fn new(image: Vec<u8>, content_type: Option<String>) -> Self {
+        CaptchaChallenge {
+            image,
+            content_type,
+        }
+    }
+
+    /// Get the bytes of the captcha image.
+    pub fn image(&self) -> &[u8] {
+        &self.image
+    }
+
+    /// Get the content type of the captcha image, e.g. `image/jpeg`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+}
+
+/// A solver answering [`CaptchaChallenge`]s automatically during login,
+/// e.g. by an OCR service.
+///
+/// It's implemented for closures taking a [`CaptchaChallenge`] and returning
+/// a future of the answer. Returning [`None`] gives up the login request.
+///
+/// Solvers are only consulted by the asynchronous [`Session`](crate::session::Session),
+/// to prompt the user instead, answer the challenge returned in the status by
+/// [`Credential::with_captcha_answer`](crate::auth::Credential::with_captcha_answer).
+///
+/// # Examples
+///
+/// ```
+/// # use neust::auth::{CaptchaChallenge, Credential};
+/// # async fn recognize(image: &[u8]) -> Option<String> { None }
+/// let credential = Credential::new("username", "password").with_captcha_solver(
+///     |challenge: CaptchaChallenge| async move { recognize(challenge.image()).await },
+/// );
+/// ```
+#[async_trait]
+pub trait CaptchaSolver: Send + Sync {
+    /// Solve `challenge`, returns the answer.
+    async fn solve(&self, challenge: &CaptchaChallenge) -> Option<String>;
+}
+
+#[async_trait]
+impl<F, Fut> CaptchaSolver for F
+where
+    F: Fn(CaptchaChallenge) -> Fut + Send + Sync,
+    Fut: Future<Output = Option<String>> + Send,
+{
+    async fn solve(&self, challenge: &CaptchaChallenge) -> Option<String> {
+        self(challenge.clone()).await
+    }
+}
+
+/// A shared [`CaptchaSolver`] held by [`Credential`](crate::auth::Credential).
+#[derive(Clone)]
+pub(crate) struct SharedSolver(pub(crate) Arc<dyn CaptchaSolver>);
+
+impl Debug for SharedSolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CaptchaSolver")
+    }
+}
+
+/// The captcha field found in the login page.
+#[derive(Debug)]
+pub(crate) struct CaptchaField {
+    pub(crate) name: String,
+    pub(crate) image_url: Url,
+}
+
+impl CaptchaField {
+    /// Find the captcha input and image in the login page located at `page_url`.
+    ///
+    /// The image is the one with id `codeImage`, or else the one served from a `code` or
+    /// `captcha` path, so that other images like the wechat QR code are never taken.
+    pub(crate) fn find(page_url: &Url, html: &str) -> Option<CaptchaField> {
+        lazy_static! {
+            static ref INPUT_RE: Regex =
+                Regex::new(r#"<input[^>]*\sname="(captcha|code|authcode)""#).unwrap();
+            static ref IMAGE_RE: Regex = Regex::new(r#"<img\s[^>]*>"#).unwrap();
+            static ref SRC_RE: Regex = Regex::new(r#"\ssrc="([^"]+)""#).unwrap();
+            static ref ID_RE: Regex = Regex::new(r#"\sid="codeImage""#).unwrap();
+            static ref PATH_RE: Regex =
+                Regex::new(r#"^[^?#]*/(?:code|captcha)(?:[?#]|$)"#).unwrap();
+        }
+
+        let name = INPUT_RE.captures(html)?.get(1)?.as_str().to_owned();
+
+        let images: Vec<(&str, &str)> = IMAGE_RE
+            .find_iter(html)
+            .filter_map(|tag| {
+                let tag = tag.as_str();
+                Some((tag, SRC_RE.captures(tag)?.get(1)?.as_str()))
+            })
+            .collect();
+        let src = images
+            .iter()
+            .find(|(tag, _)| ID_RE.is_match(tag))
+            .or_else(|| images.iter().find(|(_, src)| PATH_RE.is_match(src)))?
+            .1;
+        let image_url = page_url.join(src).ok()?;
+
+        Some(CaptchaField { name, image_url })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::auth::captcha::CaptchaField;
+
+    #[test]
+    fn test_find_captcha_field() {
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/login").unwrap();
+
+        let html = r#"<form id="loginForm" action="/tpass/login" method="post">
+<input type="text" id="un" name="un" />
+<input type="text" id="code" name="code" maxlength="4" />
+<img id="codeImage" src="/tpass/code?t=1" alt="" />
+</form>"#;
+        let field = CaptchaField::find(&url, html).unwrap();
+        assert_eq!(field.name, "code");
+        assert_eq!(
+            field.image_url.as_str(),
+            "https://pass.neu.edu.cn/tpass/code?t=1"
+        );
+
+        let html = r#"<form id="loginForm" action="/tpass/login" method="post">
+<input type="text" id="un" name="un" />
+<img src="/tpass/comm/neu/image/logo.png" />
+</form>"#;
+        assert!(CaptchaField::find(&url, html).is_none());
+
+        // the wechat QR code is not the captcha even though its url contains `code`.
+        let html = r#"<form id="loginForm" action="/tpass/login" method="post">
+<input type="text" id="code" name="code" maxlength="4" />
+<img id="qrcodeImage" src="/tpass/qrcode?uuid=1" />
+<img src="/tpass/code?t=1" alt="" />
+</form>"#;
+        let field = CaptchaField::find(&url, html).unwrap();
+        assert_eq!(
+            field.image_url.as_str(),
+            "https://pass.neu.edu.cn/tpass/code?t=1"
+        );
+
+        let html = r#"<form id="loginForm" action="/tpass/login" method="post">
+<input type="text" id="code" name="code" maxlength="4" />
+<img src="/tpass/qrcode?uuid=1" />
+<img id="codeImage" src="/tpass/verify?t=1" />
+</form>"#;
+        let field = CaptchaField::find(&url, html).unwrap();
+        assert_eq!(
+            field.image_url.as_str(),
+            "https://pass.neu.edu.cn/tpass/verify?t=1"
+        );
+
+        let html = r#"<form id="loginForm" action="/tpass/login" method="post">
+<input type="text" id="code" name="code" maxlength="4" />
+<img src="/tpass/qrcode?uuid=1" />
+</form>"#;
+        assert!(CaptchaField::find(&url, html).is_none());
+    }
+}
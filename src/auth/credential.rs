@@ -1,13 +1,17 @@
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 
 use async_trait::async_trait;
-use lazy_static::lazy_static;
-use regex::Regex;
-use reqwest::{Client, Request};
-use sealed::sealed;
+use reqwest::Url;
 
+use crate::auth::captcha::{CaptchaField, CaptchaSolver, SharedSolver};
+use crate::auth::form::Form;
+#[cfg(feature = "blocking")]
+use crate::blocking::Session as BlockingSession;
 use crate::endpoint::Endpoint;
 use crate::error::{Error, Result};
+use crate::flow::CredentialLogin;
+use crate::page;
 use crate::session::Session;
 use crate::status::UserStatus;
 
@@ -35,10 +39,14 @@ use crate::status::UserStatus;
 /// # }
 /// ```
 ///
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Credential {
     username: String,
     password: String,
+    captcha_solver: Option<SharedSolver>,
+    captcha_answer: Option<String>,
+    #[cfg(feature = "des-encrypt")]
+    encrypted: bool,
 }
 
 impl Credential {
@@ -47,70 +55,588 @@ impl Credential {
         Credential {
             username: username.into(),
             password: password.into(),
+            captcha_solver: None,
+            captcha_answer: None,
+            #[cfg(feature = "des-encrypt")]
+            encrypted: false,
         }
     }
 
-    fn build_login_request(&self, client: &Client, url: &str, lt: &str) -> Result<Request> {
-        Ok(client
-            .post(url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(format!(
-                "rsa={}{}{}&ul={}&pl={}&lt={}&execution=e1s1&_eventId=submit",
-                self.username,
-                self.password,
-                lt,
-                self.username.len(),
-                self.password.len(),
-                lt
-            ))
-            .build()?)
+    /// Submit the password encrypted by DES, exactly as the login page of the CAS does
+    /// in the browser. Disabled by default, which submits the password in plaintext.
+    #[cfg(feature = "des-encrypt")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "des-encrypt")))]
+    pub fn encrypted(mut self, enabled: bool) -> Self {
+        self.encrypted = enabled;
+        self
     }
-}
 
-#[sealed]
-#[async_trait]
-impl crate::session::AuthMethod for Credential {
-    async fn execute(&self, session: &Session, endpoint: &Endpoint) -> Result<UserStatus> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r#"LT-[0-9a-zA-Z-]+-tpass"#).unwrap();
+    /// Build the `rsa` field, which the login page computes by
+    /// `strEnc(username + password + lt, "1", "2", "3")`.
+    fn build_rsa(&self, lt: &str) -> String {
+        let rsa = format!("{}{}{}", self.username, self.password, lt);
+
+        #[cfg(feature = "des-encrypt")]
+        if self.encrypted {
+            return crate::auth::des::str_enc(&rsa, &["1", "2", "3"]);
         }
 
-        let client = session.client();
+        rsa
+    }
 
-        let pre_response = client
-            .execute(client.get(endpoint.login_url).build()?)
-            .await?;
+    /// Use `solver` to answer the captcha when the CAS requires one.
+    ///
+    /// Without a solver, or if the solver gives up, login returns [`UserStatus::Rejected`] with
+    /// [`RejectReason::CaptchaRequired`](crate::status::RejectReason::CaptchaRequired)
+    /// carrying the challenge when a captcha is required.
+    ///
+    /// The blocking session runs the solver on a runtime of the current thread,
+    /// see [`neust::blocking`](crate::blocking).
+    ///
+    /// See also [`CaptchaSolver`].
+    pub fn with_captcha_solver(mut self, solver: impl CaptchaSolver + 'static) -> Self {
+        self.captcha_solver = Some(SharedSolver(Arc::new(solver)));
+        self
+    }
 
-        let pre_final_url = pre_response.url().as_str().to_owned();
+    /// Answer the captcha returned in
+    /// [`RejectReason::CaptchaRequired`](crate::status::RejectReason::CaptchaRequired)
+    /// by a previous login in the same session, which takes precedence over the solver.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::auth::Credential;
+    /// # use neust::{RejectReason, Session};
+    /// # fn prompt(image: &[u8]) -> String { String::new() }
+    /// let session = Session::new();
+    /// let credential = Credential::new("username", "password");
+    /// let mut status = session.login(&credential).await?;
+    /// if let Some(RejectReason::CaptchaRequired {
+    ///     challenge: Some(challenge),
+    /// }) = status.get_reject_reason()
+    /// {
+    ///     let answer = prompt(challenge.image());
+    ///     status = session.login(&credential.with_captcha_answer(answer)).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_captcha_answer(mut self, answer: impl Into<String>) -> Self {
+        self.captcha_answer = Some(answer.into());
+        self
+    }
 
-        if !pre_final_url.starts_with(endpoint.login_url) {
+    pub(crate) fn captcha_solver(&self) -> Option<&SharedSolver> {
+        self.captcha_solver.as_ref()
+    }
+
+    pub(crate) fn captcha_answer(&self) -> Option<&str> {
+        self.captcha_answer.as_deref()
+    }
+
+    /// Build the fields of login form, filling the hidden inputs of the login page.
+    pub(crate) fn build_login_form(
+        &self,
+        page: &LoginPage,
+        captcha_answer: Option<&str>,
+    ) -> Vec<(String, String)> {
+        let mut fields = page.form.hidden_fields.clone();
+
+        set_field(&mut fields, "rsa", self.build_rsa(&page.lt));
+        set_field(&mut fields, "ul", self.username.len().to_string());
+        set_field(&mut fields, "pl", self.password.len().to_string());
+
+        if let (Some(captcha), Some(answer)) = (&page.captcha, captcha_answer) {
+            set_field(&mut fields, &captcha.name, answer.to_owned());
+        }
+
+        fields
+    }
+}
+
+/// Set the value of field `name`, appending it if absent.
+fn set_field(fields: &mut Vec<(String, String)>, name: &str, value: String) {
+    match fields.iter_mut().find(|(n, _)| n == name) {
+        Some((_, v)) => *v = value,
+        None => fields.push((name.to_owned(), value)),
+    }
+}
+
+/// The pre-login page of the CAS.
+pub(crate) struct LoginPage {
+    pub(crate) form: Form,
+    pub(crate) lt: String,
+    pub(crate) captcha: Option<CaptchaField>,
+}
+
+impl LoginPage {
+    pub(crate) fn parse(
+        endpoint: &Endpoint,
+        pre_final_url: &Url,
+        pre_body: &str,
+    ) -> Result<LoginPage> {
+        if !pre_final_url.as_str().starts_with(endpoint.login_url()) {
             return Err(Error::StatusConflict);
         }
 
-        let pre_body = pre_response.text().await?;
+        let form = Form::find(pre_final_url, pre_body, "lt")
+            .ok_or_else(|| Error::parse_page_error(pre_final_url.as_str()))?;
 
-        let lt = RE
-            .find(&pre_body)
-            .map(|s| s.as_str())
-            .ok_or_else(|| Error::parse_page_error(pre_final_url))?;
+        let missing = form.missing_fields(page::LOGIN_FORM_FIELDS);
+        if !missing.is_empty() {
+            return Err(Error::MissingFormFields {
+                url: pre_final_url.to_string(),
+                fields: missing,
+            });
+        }
 
-        let request = self.build_login_request(client, endpoint.login_url, lt)?;
+        Ok(LoginPage {
+            lt: form.hidden_value("lt").unwrap_or_default().to_owned(),
+            captcha: CaptchaField::find(pre_final_url, pre_body),
+            form,
+        })
+    }
+}
+
+#[async_trait]
+impl crate::session::AuthMethod for Credential {
+    async fn execute(&self, session: &Session, endpoint: &Endpoint) -> Result<UserStatus> {
+        session
+            .drive(CredentialLogin::new(self, session.cookie_jar(), endpoint))
+            .await
+    }
+}
 
-        client.execute(request).await?.text().await?;
+#[cfg(feature = "blocking")]
+impl crate::blocking::AuthMethod for Credential {
+    fn execute(&self, session: &BlockingSession, endpoint: &Endpoint) -> Result<UserStatus> {
+        session.drive(CredentialLogin::new(self, session.cookie_jar(), endpoint))
+    }
+}
 
-        session._check_status(endpoint).await
+impl PartialEq for Credential {
+    fn eq(&self, other: &Self) -> bool {
+        self.username == other.username && self.password == other.password
     }
 }
 
+impl Eq for Credential {}
+
 impl Display for Credential {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "credential#{}", self.username)
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Credential {
+    /// Serialize the username only, use [`with_password`] to include the password.
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        CredentialRef {
+            username: &self.username,
+            password: None,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Credential {
+    /// Deserialize a credential, which fails if the password is omitted.
+    ///
+    /// Since the password is not serialized by default, serialize the credential by
+    /// [`with_password`] to deserialize it later.
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let owned = CredentialOwned::deserialize(deserializer)?;
+        Ok(Credential::new(owned.username, owned.password))
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct CredentialRef<'a> {
+    username: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<&'a str>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct CredentialOwned {
+    username: String,
+    password: String,
+}
+
+/// Serialize [`Credential`] with the password, which is omitted by default.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use neust::auth::Credential;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Job {
+///     #[serde(with = "neust::auth::with_password")]
+///     credential: Credential,
+/// }
+///
+/// let job = Job {
+///     credential: Credential::new("username", "password"),
+/// };
+/// let json = serde_json::to_string(&job).unwrap();
+/// assert!(json.contains("password"));
+/// # }
+/// ```
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod with_password {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{Credential, CredentialRef};
+
+    /// Serialize `credential` with the password.
+    pub fn serialize<S: Serializer>(
+        credential: &Credential,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        CredentialRef {
+            username: &credential.username,
+            password: Some(&credential.password),
+        }
+        .serialize(serializer)
+    }
+
+    /// Deserialize a credential, the same as [`Credential`] does.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Credential, D::Error> {
+        Credential::deserialize(deserializer)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use reqwest::Url;
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use reqwest::{header::SET_COOKIE, Method, StatusCode};
+
+    use crate::auth::captcha::{CaptchaChallenge, CaptchaField};
+    use crate::auth::credential::LoginPage;
+    use crate::auth::form::Form;
     use crate::auth::Credential;
+    use crate::endpoint::Endpoint;
+    use crate::error::Error;
+    use crate::retry::RetryPolicy;
+    use crate::session::Session;
+    use crate::status::{RejectReason, UserStatus};
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    static LOGIN_URL: &str = "https://pass.neu.edu.cn/tpass/login";
+
+    fn login_page(extra: &str) -> String {
+        format!(
+            r#"<html><head><title>智慧东大--统一身份认证</title></head><body>
+<form id="loginForm" action="/tpass/login" method="post">
+<input type="hidden" id="lt" name="lt" value="LT-1-tpass"/>
+<input type="hidden" name="execution" value="e1s1"/>
+<input type="hidden" name="_eventId" value="submit"/>
+{}
+</form></body></html>"#,
+            extra
+        )
+    }
+
+    #[tokio::test]
+    async fn test_credential_login() {
+        let portal = r#"<html><head><title>个人中心</title></head>
+<script>var id_number = "20180000";</script></html>"#;
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(login_page("")))
+                .expect(
+                    Method::POST,
+                    LOGIN_URL,
+                    MockResponse::redirect("https://portal.neu.edu.cn/tp_up/")
+                        .header(SET_COOKIE, "CASTGC=TGT-20180000-1-tpass; Path=/tpass/"),
+                )
+                .expect(
+                    Method::GET,
+                    "https://portal.neu.edu.cn/tp_up/",
+                    MockResponse::ok(portal),
+                )
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(portal)),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        let status = session
+            .login(&Credential::new("20180000", "password"))
+            .await
+            .unwrap();
+        assert_eq!(status.get_username(), Some("20180000"));
+        assert_eq!(status.get_token(), Some("TGT-20180000-1-tpass"));
+
+        transport.assert_done();
+        let form = transport.requests()[1].form.clone().unwrap();
+        assert!(form.contains(&("rsa".to_owned(), "20180000passwordLT-1-tpass".to_owned())));
+        assert_eq!(
+            transport.cookies()[3].as_deref(),
+            Some("CASTGC=TGT-20180000-1-tpass")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credential_login_rejected() {
+        let table = vec![
+            ("用户名或密码错误", RejectReason::WrongCredentials),
+            ("账号已被锁定，请30分钟后再试", RejectReason::AccountLocked),
+            (
+                "登录失败次数过多，请稍后再试",
+                RejectReason::TooManyAttempts,
+            ),
+        ];
+
+        for (message, expected) in table {
+            let error = format!(r#"<span id="errormsg">{}</span>"#, message);
+            let transport = Arc::new(
+                MockTransport::new()
+                    .expect(Method::GET, LOGIN_URL, MockResponse::ok(login_page("")))
+                    .expect(
+                        Method::POST,
+                        LOGIN_URL,
+                        MockResponse::ok(login_page(&error)),
+                    ),
+            );
+            let session = Session::with_transport(transport.clone());
+
+            let status = session
+                .login(&Credential::new("20180000", "wrong"))
+                .await
+                .unwrap();
+            assert!(status.is_rejected());
+            assert_eq!(status.get_reject_reason(), Some(&expected));
+            transport.assert_done();
+        }
+
+        let captcha = r#"<input type="text" name="code"/><img src="/tpass/code"/>"#;
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    LOGIN_URL,
+                    MockResponse::ok(login_page(captcha)),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/code",
+                    MockResponse::ok(vec![0xff, 0xd8]),
+                ),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        let status = session
+            .login(&Credential::new("20180000", "password"))
+            .await
+            .unwrap();
+        match status {
+            UserStatus::Rejected {
+                reason:
+                    Some(RejectReason::CaptchaRequired {
+                        challenge: Some(challenge),
+                    }),
+            } => assert_eq!(challenge.image(), [0xff, 0xd8]),
+            other => panic!("unexpected status: {:?}", other),
+        }
+        transport.assert_done();
+    }
+
+    #[tokio::test]
+    async fn test_credential_captcha() {
+        let credential = Credential::new("20180000", "password");
+        let challenge = CaptchaChallenge::new(vec![0xff, 0xd8], Some("image/jpeg".to_owned()));
+        assert!(credential.captcha_solver().is_none());
+
+        let credential = credential.with_captcha_solver(|c: CaptchaChallenge| async move {
+            assert_eq!(c.content_type(), Some("image/jpeg"));
+            Some("a1b2".to_owned())
+        });
+        let solver = credential.captcha_solver().unwrap();
+        let answer = solver.0.solve(&challenge).await;
+        assert_eq!(answer.as_deref(), Some("a1b2"));
+
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/login").unwrap();
+        let page = LoginPage {
+            form: Form {
+                action: url.join("/tpass/login;jsessionid=abc").unwrap(),
+                hidden_fields: vec![
+                    ("rsa".to_owned(), "".to_owned()),
+                    ("lt".to_owned(), "LT-1-tpass".to_owned()),
+                    ("execution".to_owned(), "e2s1".to_owned()),
+                    ("_eventId".to_owned(), "submit".to_owned()),
+                ],
+                password_fields: vec![],
+                input_names: vec![],
+            },
+            lt: "LT-1-tpass".to_owned(),
+            captcha: Some(CaptchaField {
+                name: "code".to_owned(),
+                image_url: url.join("/tpass/code").unwrap(),
+            }),
+        };
+        let form = credential.build_login_form(&page, answer.as_deref());
+        let expected = [
+            ("rsa", "20180000passwordLT-1-tpass"),
+            ("lt", "LT-1-tpass"),
+            ("execution", "e2s1"),
+            ("_eventId", "submit"),
+            ("ul", "8"),
+            ("pl", "8"),
+            ("code", "a1b2"),
+        ];
+        assert_eq!(form.len(), expected.len());
+        for ((name, value), (expected_name, expected_value)) in form.iter().zip(expected) {
+            assert_eq!(name, expected_name);
+            assert_eq!(value, expected_value);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_credential_login_retry() {
+        let unavailable = || MockResponse::ok("").status(StatusCode::SERVICE_UNAVAILABLE);
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, LOGIN_URL, unavailable())
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(login_page("")))
+                .expect(Method::POST, LOGIN_URL, unavailable())
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(login_page(""))),
+        );
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let session = Session::with_transport(transport.clone()).with_retry_policy(policy);
+
+        let status = session
+            .login(&Credential::new("20180000", "password"))
+            .await
+            .unwrap();
+        assert!(status.is_rejected());
+        transport.assert_done();
+    }
+
+    #[test]
+    fn test_parse_login_page() {
+        let endpoint = Endpoint::direct();
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/login?service=x").unwrap();
+
+        let html = r#"<form id="loginForm" action="/tpass/login?service=x" method="post">
+<input type="hidden" id="lt" name="lt" value="LT-1-tpass"/>
+<input type="hidden" name="execution" value="e1s1"/>
+<input type="hidden" name="_eventId" value="submit"/>
+</form>"#;
+        let page = LoginPage::parse(endpoint, &url, html).unwrap();
+        assert_eq!(page.lt, "LT-1-tpass");
+        assert_eq!(page.form.action, url);
+        assert!(page.captcha.is_none());
+
+        let html = r#"<form id="loginForm" action="/tpass/login" method="post">
+<input type="hidden" id="lt" name="lt" value=""/>
+<input type="hidden" name="_eventId" value="submit"/>
+</form>"#;
+        match LoginPage::parse(endpoint, &url, html) {
+            Err(Error::MissingFormFields { fields, .. }) => {
+                assert_eq!(fields, ["lt", "execution"])
+            }
+            other => panic!("unexpected result: {:?}", other.err()),
+        }
+
+        assert!(matches!(
+            LoginPage::parse(endpoint, &url, "<html></html>"),
+            Err(Error::ParsePageError { .. })
+        ));
+
+        let url = Url::parse("https://portal.neu.edu.cn/tp_up/view?m=up").unwrap();
+        assert!(matches!(
+            LoginPage::parse(endpoint, &url, html),
+            Err(Error::StatusConflict)
+        ));
+    }
+
+    #[test]
+    fn test_parse_login_page_fixtures() {
+        let endpoint = Endpoint::direct();
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/login").unwrap();
+
+        let html = include_str!("../../tests/fixtures/login.html");
+        let page = LoginPage::parse(endpoint, &url, html).unwrap();
+        assert_eq!(page.lt, "LT-1894267-q0NFbRLqWjcxUyq9xKdfMzHcDbLmXe-tpass");
+        assert_eq!(page.form.password_fields, ["pd"]);
+        assert!(page.captcha.is_none());
+
+        let html = include_str!("../../tests/fixtures/login_captcha.html");
+        let page = LoginPage::parse(endpoint, &url, html).unwrap();
+        let captcha = page.captcha.unwrap();
+        assert_eq!(captcha.name, "code");
+        assert_eq!(
+            captcha.image_url.as_str(),
+            "https://pass.neu.edu.cn/tpass/code?t=1633058917253"
+        );
+
+        let html = include_str!("../../tests/fixtures/need_reset.html");
+        assert!(matches!(
+            LoginPage::parse(endpoint, &url, html),
+            Err(Error::MissingFormFields { .. })
+        ));
+    }
+
+    #[cfg(feature = "des-encrypt")]
+    #[test]
+    fn test_credential_encrypted() {
+        let credential = Credential::new("20180000", "password");
+        assert_eq!(
+            credential.build_rsa("LT-1-tpass"),
+            "20180000passwordLT-1-tpass"
+        );
+
+        let credential = credential.encrypted(true);
+        assert_eq!(
+            credential.build_rsa("LT-1-tpass"),
+            [
+                "3C85BDEB3E13F766A65015EA55D039D9A4D83E63A96B617A",
+                "4AB9F5AC9E2F4E032628408C936E0ED8512B74403EBD9448A4FF42B96688B502",
+            ]
+            .concat()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_credential_serde() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct WithPassword(#[serde(with = "crate::auth::with_password")] Credential);
+
+        let credential = Credential::new("20180000", "password");
+
+        let json = serde_json::to_string(&credential).unwrap();
+        assert_eq!(json, r#"{"username":"20180000"}"#);
+        let err = serde_json::from_str::<Credential>(&json).unwrap_err();
+        assert!(err.to_string().contains("missing field `password`"));
+
+        let json = serde_json::to_string(&WithPassword(credential.clone())).unwrap();
+        assert_eq!(json, r#"{"username":"20180000","password":"password"}"#);
+        let restored: WithPassword = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.0, credential);
+    }
 
     #[test]
     fn test_credential_cmp() {
@@ -0,0 +1,87 @@
+//! The `strEnc` function in `des.js` of the CAS login page.
+
+use des::{
+    cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit},
+    Des,
+};
+
+/// Encrypts `data` the same as `strEnc(data, keys[0], keys[1], ...)` in `des.js`.
+///
+/// Both `data` and keys are split into chunks of 4 UTF-16 code units, each zero-padded
+/// to a 64-bit block. Every block of `data` is encrypted by DES with each key chunk in order,
+/// and the results are joined as uppercase hex.
+pub(crate) fn str_enc(data: &str, keys: &[&str]) -> String {
+    let ciphers = keys
+        .iter()
+        .flat_map(|key| to_blocks(key))
+        .map(|key| Des::new(&GenericArray::from(key)))
+        .collect::<Vec<_>>();
+
+    to_blocks(data)
+        .into_iter()
+        .map(|block| {
+            let mut block = GenericArray::from(block);
+            for cipher in &ciphers {
+                cipher.encrypt_block(&mut block);
+            }
+            block
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// Split `s` into 64-bit blocks of 4 UTF-16 code units in big endian.
+fn to_blocks(s: &str) -> Vec<[u8; 8]> {
+    s.encode_utf16()
+        .collect::<Vec<_>>()
+        .chunks(4)
+        .map(|units| {
+            let mut block = [0; 8];
+            for (i, unit) in units.iter().enumerate() {
+                block[i * 2..i * 2 + 2].copy_from_slice(&unit.to_be_bytes());
+            }
+            block
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::des::str_enc;
+
+    #[test]
+    fn test_str_enc() {
+        let table = vec![
+            ("", "".to_owned()),
+            ("a", "D127D6E48726B9FA".to_owned()),
+            ("abcd", "5F3739992429F638".to_owned()),
+            (
+                "20180000passwordLT-1-tpass",
+                [
+                    "3C85BDEB3E13F766A65015EA55D039D9A4D83E63A96B617A",
+                    "4AB9F5AC9E2F4E032628408C936E0ED8512B74403EBD9448A4FF42B96688B502",
+                ]
+                .concat(),
+            ),
+            (
+                "20180000密码LT-1-tpass",
+                [
+                    "3C85BDEB3E13F766A65015EA55D039D9",
+                    "DACC70F256EBCCD0294E62A6B2621BA7A4D83E63A96B617A",
+                ]
+                .concat(),
+            ),
+        ];
+
+        for (data, expected) in table {
+            assert_eq!(str_enc(data, &["1", "2", "3"]), expected)
+        }
+
+        assert_eq!(
+            str_enc("abcdefg", &["12345", "key"]),
+            "4FE904266BA4E6A2F52CE822E1D71028"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+
+/// A `<form>` parsed from a page.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Form {
+    /// The absolute url the form submits to.
+    pub(crate) action: Url,
+    /// Name and value of hidden inputs, in document order.
+    pub(crate) hidden_fields: Vec<(String, String)>,
+    /// Names of password inputs, in document order.
+    pub(crate) password_fields: Vec<String>,
+    /// Names of all inputs, in document order.
+    pub(crate) input_names: Vec<String>,
+}
+
+impl Form {
+    /// Find the first form in `html` containing an input named `marker`,
+    /// or the first form if there is no such form.
+    pub(crate) fn find(page_url: &Url, html: &str, marker: &str) -> Option<Form> {
+        let mut forms = Form::find_all(page_url, html);
+
+        let index = forms
+            .iter()
+            .position(|form| form.input_names.iter().any(|name| name == marker))
+            .unwrap_or(0);
+
+        match forms.len() > index {
+            true => Some(forms.swap_remove(index)),
+            false => None,
+        }
+    }
+
+    /// Find all forms in `html`, skipping those with malformed actions.
+    pub(crate) fn find_all(page_url: &Url, html: &str) -> Vec<Form> {
+        lazy_static! {
+            static ref FORM_RE: Regex = Regex::new(r"(?is)<form\b([^>]*)>(.*?)</form>").unwrap();
+            static ref INPUT_RE: Regex = Regex::new(r"(?is)<input\b([^>]*)>").unwrap();
+        }
+
+        FORM_RE
+            .captures_iter(html)
+            .filter_map(|cap| {
+                let attributes = parse_attributes(cap.get(1).unwrap().as_str());
+                let inputs = INPUT_RE
+                    .captures_iter(cap.get(2).unwrap().as_str())
+                    .map(|input| parse_attributes(input.get(1).unwrap().as_str()));
+                Form::parse(page_url, &attributes, inputs)
+            })
+            .collect()
+    }
+
+    fn parse(
+        page_url: &Url,
+        attributes: &[(String, String)],
+        inputs: impl Iterator<Item = Vec<(String, String)>>,
+    ) -> Option<Form> {
+        let action = match get_attribute(attributes, "action") {
+            Some(action) if !action.is_empty() => page_url.join(&unescape(action)).ok()?,
+            _ => page_url.clone(),
+        };
+
+        let mut hidden_fields = Vec::new();
+        let mut password_fields = Vec::new();
+        let mut input_names = Vec::new();
+        for input in inputs {
+            let name = match get_attribute(&input, "name") {
+                Some(name) => unescape(name),
+                None => continue,
+            };
+            match get_attribute(&input, "type").map(|t| t.to_ascii_lowercase()) {
+                Some(t) if t == "hidden" => {
+                    let value = get_attribute(&input, "value").map(unescape);
+                    hidden_fields.push((name.clone(), value.unwrap_or_default()));
+                }
+                Some(t) if t == "password" => password_fields.push(name.clone()),
+                _ => {}
+            }
+            input_names.push(name);
+        }
+
+        Some(Form {
+            action,
+            hidden_fields,
+            password_fields,
+            input_names,
+        })
+    }
+
+    /// Get the value of the hidden input named `name`.
+    pub(crate) fn hidden_value(&self, name: &str) -> Option<&str> {
+        self.hidden_fields
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Get the names in `expected` that are not hidden inputs of the form, or have empty values.
+    pub(crate) fn missing_fields(&self, expected: &[&str]) -> Vec<String> {
+        expected
+            .iter()
+            .filter(|name| !matches!(self.hidden_value(name), Some(v) if !v.is_empty()))
+            .map(|name| name.to_string())
+            .collect()
+    }
+}
+
+fn parse_attributes(raw: &str) -> Vec<(String, String)> {
+    lazy_static! {
+        static ref ATTRIBUTE_RE: Regex =
+            Regex::new(r#"([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+    }
+
+    ATTRIBUTE_RE
+        .captures_iter(raw)
+        .map(|cap| {
+            let value = cap
+                .get(2)
+                .or_else(|| cap.get(3))
+                .or_else(|| cap.get(4))
+                .map(|v| v.as_str())
+                .unwrap_or_default();
+            (cap[1].to_ascii_lowercase(), value.to_owned())
+        })
+        .collect()
+}
+
+fn get_attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn unescape(raw: &str) -> String {
+    raw.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use crate::auth::form::Form;
+
+    #[test]
+    fn test_find_form() {
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/login?service=x").unwrap();
+        let html = r#"<form id="search" action="/search"><input name="q"></form>
+<form id="loginForm" action="/tpass/login;jsessionid=abc?service=x&amp;a=1" method="post">
+    <input type="text" id="un" name="un" placeholder="username"/>
+    <input type="password" id="pd" name="pd"/>
+    <input type="hidden" id="rsa" name="rsa"/>
+    <input type="hidden" id="ul" name="ul"/>
+    <input type="hidden" id="pl" name="pl"/>
+    <input type="hidden" id="lt" name="lt" value="LT-1-tpass"/>
+    <input type="HIDDEN" name='execution' value='e2s1'/>
+    <input type=hidden name=_eventId value=submit>
+</form>"#;
+
+        let form = Form::find(&url, html, "lt").unwrap();
+        assert_eq!(
+            form.action.as_str(),
+            "https://pass.neu.edu.cn/tpass/login;jsessionid=abc?service=x&a=1"
+        );
+        assert_eq!(form.hidden_value("lt"), Some("LT-1-tpass"));
+        assert_eq!(form.hidden_value("execution"), Some("e2s1"));
+        assert_eq!(form.hidden_value("_eventId"), Some("submit"));
+        assert_eq!(form.hidden_value("rsa"), Some(""));
+        assert_eq!(
+            form.input_names,
+            ["un", "pd", "rsa", "ul", "pl", "lt", "execution", "_eventId"]
+        );
+        assert!(form.missing_fields(&["lt", "execution"]).is_empty());
+        assert_eq!(
+            form.missing_fields(&["lt", "rsa", "captcha"]),
+            ["rsa", "captcha"]
+        );
+
+        let form = Form::find(&url, r#"<form><input name="q"></form>"#, "lt").unwrap();
+        assert_eq!(form.action, url);
+        assert!(Form::find(&url, "<html></html>", "lt").is_none());
+    }
+}
@@ -1,11 +1,18 @@
 //! Several implementations for [`AuthMethod`](crate::session::AuthMethod).
 
+pub use captcha::{CaptchaChallenge, CaptchaSolver};
+#[cfg(feature = "serde")]
+pub use credential::with_password;
 pub use credential::Credential;
 pub use token::Token;
 #[cfg(feature = "wechat")]
 pub use wechat::Wechat;
 
-mod credential;
+pub(crate) mod captcha;
+pub(crate) mod credential;
+#[cfg(feature = "des-encrypt")]
+pub(crate) mod des;
+pub(crate) mod form;
 mod token;
 #[cfg(feature = "wechat")]
-mod wechat;
+pub(crate) mod wechat;
@@ -1,10 +1,13 @@
 use std::fmt::{Display, Formatter};
 
 use async_trait::async_trait;
-use sealed::sealed;
 
+#[cfg(feature = "blocking")]
+use crate::blocking::Session as BlockingSession;
 use crate::endpoint::Endpoint;
 use crate::error::Result;
+use crate::flow::TokenLogin;
+use crate::jar::CookieJar;
 use crate::session::Session;
 use crate::status::UserStatus;
 
@@ -35,25 +38,38 @@ use crate::status::UserStatus;
 /// ```
 ///
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token(String);
 
 impl Token {
     /// Creates a [`Token`].
     pub fn new(token: impl Into<String>) -> Self {
-        Token { 0: token.into() }
+        Token(token.into())
+    }
+}
+
+impl Token {
+    pub(crate) fn store(&self, cookie_jar: &CookieJar, endpoint: &Endpoint) {
+        cookie_jar.add_cookie_str(
+            format!("{}={}", endpoint.cookie_name(), self.0).as_str(),
+            endpoint.cookie_url(),
+        );
     }
 }
 
-#[sealed]
 #[async_trait]
 impl crate::session::AuthMethod for Token {
     async fn execute(&self, session: &Session, endpoint: &Endpoint) -> Result<UserStatus> {
-        session.cookie_jar().add_cookie_str(
-            format!("{}={}", endpoint.cookie_name, self.0).as_str(),
-            &endpoint.cookie_url,
-        );
+        session
+            .drive(TokenLogin::new(self, session.cookie_jar(), endpoint))
+            .await
+    }
+}
 
-        session._check_status(endpoint).await
+#[cfg(feature = "blocking")]
+impl crate::blocking::AuthMethod for Token {
+    fn execute(&self, session: &BlockingSession, endpoint: &Endpoint) -> Result<UserStatus> {
+        session.drive(TokenLogin::new(self, session.cookie_jar(), endpoint))
     }
 }
 
@@ -65,7 +81,40 @@ impl Display for Token {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use reqwest::Method;
+
     use crate::auth::Token;
+    use crate::session::Session;
+    use crate::status::RejectReason;
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    #[tokio::test]
+    async fn test_token_login() {
+        let login_page = "<html><head><title>智慧东大--统一身份认证</title></head></html>";
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            "https://pass.neu.edu.cn/tpass/login",
+            MockResponse::ok(login_page),
+        ));
+        let session = Session::with_transport(transport.clone());
+
+        let status = session
+            .login(&Token::new("TGT-20180000-1-tpass"))
+            .await
+            .unwrap();
+        assert_eq!(
+            status.get_reject_reason(),
+            Some(&RejectReason::TokenExpired)
+        );
+
+        transport.assert_done();
+        assert_eq!(
+            transport.cookies()[0].as_deref(),
+            Some("CASTGC=TGT-20180000-1-tpass")
+        );
+    }
 
     #[test]
     fn test_token_cmp() {
@@ -4,10 +4,12 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use async_trait::async_trait;
 use rand;
 use rand::Rng;
-use sealed::sealed;
 
+#[cfg(feature = "blocking")]
+use crate::blocking::Session as BlockingSession;
 use crate::endpoint::Endpoint;
 use crate::error::Result;
+use crate::flow::WechatLogin;
 use crate::session::Session;
 use crate::status::UserStatus;
 
@@ -38,7 +40,7 @@ static WECHAT_AUTH_URL: &str = "https://pass.neu.edu.cn/tpass/qyQrLogin";
 ///         match session.login(&wechat).await {
 ///             Ok(status) => match status {
 ///                 UserStatus::Active { .. } => break,
-///                 UserStatus::Rejected => continue,
+///                 UserStatus::Rejected { .. } => continue,
 ///                 _ => panic!("something wrong: {:?}", status)
 ///             },
 ///             Err(e) => panic!("something wrong: {:?}", e)
@@ -50,6 +52,7 @@ static WECHAT_AUTH_URL: &str = "https://pass.neu.edu.cn/tpass/qyQrLogin";
 /// ```
 ///
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(docsrs, doc(cfg(feature = "wechat")))]
 pub struct Wechat {
     uuid: String,
@@ -76,25 +79,27 @@ impl Wechat {
     }
 }
 
-#[sealed]
 #[async_trait]
 impl crate::session::AuthMethod for Wechat {
     async fn execute(&self, session: &Session, endpoint: &Endpoint) -> Result<UserStatus> {
-        let client = session.client();
-
-        let verify_request = client
-            .get(self.get_verify_url(endpoint.wechat_verify_url))
-            .build()?;
-
-        let body = client.execute(verify_request).await?.text().await?;
+        session
+            .drive(WechatLogin::new(self, session.cookie_jar(), endpoint))
+            .await
+    }
+}
 
-        match body.len() {
-            0 => Ok(UserStatus::Rejected),
-            _ => session._check_status(endpoint).await,
-        }
+#[cfg(feature = "blocking")]
+impl crate::blocking::AuthMethod for Wechat {
+    fn execute(&self, session: &BlockingSession, endpoint: &Endpoint) -> Result<UserStatus> {
+        session.drive(WechatLogin::new(self, session.cookie_jar(), endpoint))
     }
 }
 
+/// The verify url responds nothing until Wechat authorizes the login request.
+pub(crate) fn is_authorized(verify_body: &[u8]) -> bool {
+    !verify_body.is_empty()
+}
+
 impl Wechat {
     /// Get the url for authorization on Wechat.
     ///
@@ -149,7 +154,35 @@ impl Display for Wechat {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use reqwest::Method;
+
     use crate::auth::Wechat;
+    use crate::session::Session;
+    use crate::status::RejectReason;
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    #[tokio::test]
+    async fn test_wechat_pending() {
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            "https://pass.neu.edu.cn/tpass/checkQRCodeScan",
+            MockResponse::ok(""),
+        ));
+        let session = Session::with_transport(transport.clone());
+
+        let wechat = Wechat::new(Some("uuid".to_owned()));
+        let status = session.login(&wechat).await.unwrap();
+        assert_eq!(
+            status.get_reject_reason(),
+            Some(&RejectReason::PendingWechatAuthorization)
+        );
+
+        transport.assert_done();
+        let url = &transport.requests()[0].url;
+        assert!(url.query_pairs().any(|(k, v)| k == "uuid" && v == "uuid"));
+    }
 
     #[test]
     fn test_wechat_cmp() {
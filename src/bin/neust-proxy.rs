@@ -0,0 +1,300 @@
+//! A local HTTP proxy tunneling requests to intranet services through the WebVPN.
+//!
+//! This binary requires feature **neust/proxy**
+//!
+//! ```text
+//! neust-proxy <username> [address]
+//! ```
+//!
+//! The password is read from the environment variable `NEUST_PASSWORD`, or from stdin
+//! if it is not set, so that it does not show up in the process list or shell history.
+//! It is not echoed when typed in a terminal.
+//!
+//! It logs in via the CAS and the WebVPN, then listens on `address`
+//! (`127.0.0.1:8118` by default) as an HTTP proxy, e.g.
+//!
+//! ```text
+//! curl -x http://127.0.0.1:8118 http://219.216.96.4/eams/homeExt.action
+//! ```
+//!
+//! Urls of requests are encrypted for the WebVPN and sent with cookies of the session,
+//! while `Location` headers and links in HTML pages of responses are decrypted back to
+//! urls of services, so that browsers keep talking to the proxy.
+//!
+//! `CONNECT` is not supported, since the proxy has to read requests to rewrite them,
+//! so services are only reachable by `http://` urls through the proxy.
+
+use std::convert::Infallible;
+use std::env;
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::process;
+
+use hyper::body::to_bytes;
+use hyper::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CONNECTION, CONTENT_LENGTH, CONTENT_TYPE,
+    COOKIE, HOST, LOCATION, ORIGIN, REFERER, SET_COOKIE,
+};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use neust::reqwest::{cookie::CookieStore, redirect::Policy, Client, Url};
+use neust::webvpn::{self, WebVpnConfig};
+use neust::{auth::Credential, Session};
+use rtoolbox::atty::{self, Stream};
+
+/// Headers that only apply to a single connection, see RFC 7230.
+static HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// The environment variable holding the password, which is read from stdin if unset.
+static PASSWORD_ENV: &str = "NEUST_PASSWORD";
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug, Clone)]
+struct Proxy {
+    session: Session,
+    /// A client that does not follow redirects, so that browsers see them.
+    client: Client,
+    webvpn_login_url: Url,
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        fail("usage: neust-proxy <username> [address]")
+    }
+
+    let addr: SocketAddr = match args.get(2) {
+        Some(addr) => addr
+            .parse()
+            .unwrap_or_else(|e| fail(format!("invalid address {}: {}", addr, e))),
+        None => ([127, 0, 0, 1], 8118).into(),
+    };
+
+    let credential = Credential::new(&args[1], read_password(&args[1]));
+    let session = Session::new();
+
+    match session.login(&credential).await {
+        Ok(status) if status.is_active() => {}
+        Ok(status) => fail(format!("fail to login: {}", status)),
+        Err(e) => fail(format!("fail to login: {}", e)),
+    }
+    match session.login_via_webvpn(&credential).await {
+        Ok(status) if status.is_active() => {}
+        Ok(status) => fail(format!("fail to login via webvpn: {}", status)),
+        Err(e) => fail(format!("fail to login via webvpn: {}", e)),
+    }
+
+    let client = Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap_or_else(|e| fail(e));
+
+    let proxy = Proxy {
+        session,
+        client,
+        webvpn_login_url: WebVpnConfig::default().login_url(),
+    };
+
+    let make_service = make_service_fn(move |_| {
+        let proxy = proxy.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let proxy = proxy.clone();
+                async move { Ok::<_, Infallible>(proxy.handle(request).await) }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .unwrap_or_else(|e| fail(format!("fail to listen on {}: {}", addr, e)))
+        .serve(make_service);
+
+    eprintln!("neust-proxy is listening on http://{}", addr);
+    if let Err(e) = server.await {
+        fail(e)
+    }
+}
+
+impl Proxy {
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let method = request.method().clone();
+        let uri = request.uri().clone();
+
+        match self.forward(request).await {
+            Ok(response) => {
+                eprintln!("{} {} {}", method, uri, response.status().as_u16());
+                response
+            }
+            Err(e) => {
+                eprintln!("{} {} failed: {}", method, uri, e);
+                text_response(StatusCode::BAD_GATEWAY, e.to_string())
+            }
+        }
+    }
+
+    async fn forward(&self, request: Request<Body>) -> Result<Response<Body>, BoxError> {
+        if request.method() == Method::CONNECT {
+            return Ok(text_response(
+                StatusCode::NOT_IMPLEMENTED,
+                "CONNECT is not supported, request services by http:// urls instead",
+            ));
+        }
+
+        let url = match Url::parse(&request.uri().to_string()) {
+            Ok(url) if url.has_host() => url,
+            _ => {
+                return Ok(text_response(
+                    StatusCode::BAD_REQUEST,
+                    "only requests of absolute urls are accepted by the proxy",
+                ))
+            }
+        };
+        let target = webvpn::encrypt(&url)?;
+
+        let (parts, body) = request.into_parts();
+        let mut headers = parts.headers;
+        remove_hop_by_hop_headers(&mut headers);
+        // cookies of the session are sent instead, and responses are rewritten uncompressed
+        for name in [HOST, COOKIE, ORIGIN, ACCEPT_ENCODING] {
+            headers.remove(name);
+        }
+        if let Some(referer) = header_url(&headers, REFERER, &url) {
+            let referer = webvpn::encrypt(&referer).unwrap_or(referer);
+            headers.insert(REFERER, HeaderValue::from_str(referer.as_str())?);
+        }
+        if let Some(cookie) = self.session.cookie_jar().cookies(&target) {
+            headers.insert(COOKIE, cookie);
+        }
+
+        let response = self
+            .client
+            .request(parts.method, target.clone())
+            .headers(headers)
+            .body(to_bytes(body).await?)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let mut headers = response.headers().clone();
+        self.session
+            .cookie_jar()
+            .set_cookies(&mut headers.get_all(SET_COOKIE).iter(), &target);
+        remove_hop_by_hop_headers(&mut headers);
+        for name in [SET_COOKIE, CONTENT_LENGTH] {
+            headers.remove(name);
+        }
+
+        if let Some(location) = header_url(&headers, LOCATION, &target) {
+            if location
+                .as_str()
+                .starts_with(self.webvpn_login_url.as_str())
+            {
+                return Ok(text_response(
+                    StatusCode::BAD_GATEWAY,
+                    "the WebVPN session has expired, please restart neust-proxy",
+                ));
+            }
+            if let Ok(location) = webvpn::decrypt(&location) {
+                headers.insert(LOCATION, HeaderValue::from_str(location.as_str())?);
+            }
+        }
+
+        let is_html = matches!(
+            headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()),
+            Some(content_type) if content_type.starts_with("text/html")
+        );
+        let bytes = response.bytes().await?;
+        let html = match is_html {
+            true => std::str::from_utf8(&bytes)
+                .ok()
+                .map(|html| webvpn::decrypt_links(html).into_owned()),
+            false => None,
+        };
+        let body = match html {
+            Some(html) => Body::from(html),
+            // pages in other charsets are passed through as is
+            None => Body::from(bytes),
+        };
+
+        let mut response = Response::new(body);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        Ok(response)
+    }
+}
+
+/// Parse the header `name` as a url relative to `base`.
+fn header_url(headers: &HeaderMap, name: HeaderName, base: &Url) -> Option<Url> {
+    let value = headers.get(name)?.to_str().ok()?;
+    base.join(value).ok()
+}
+
+/// Remove hop-by-hop headers, including those listed in the `Connection` header.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let listed: Vec<String> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+
+    for name in HOP_BY_HOP_HEADERS
+        .iter()
+        .copied()
+        .chain(listed.iter().map(|name| name.as_str()))
+    {
+        headers.remove(name);
+    }
+}
+
+fn text_response(status: StatusCode, text: impl Into<String>) -> Response<Body> {
+    let mut response = Response::new(Body::from(text.into()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response
+}
+
+/// Read the password of `username` from [`PASSWORD_ENV`], or prompt for it on stdin.
+fn read_password(username: &str) -> String {
+    if let Ok(password) = env::var(PASSWORD_ENV) {
+        return password;
+    }
+
+    let prompt = format!("password of {}: ", username);
+    if atty::is(Stream::Stdin) {
+        return rpassword::prompt_password(prompt)
+            .unwrap_or_else(|e| fail(format!("fail to read password: {}", e)));
+    }
+
+    eprint!("{}", prompt);
+    let _ = io::stderr().flush();
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) => fail(format!("no password, set {} or enter it", PASSWORD_ENV)),
+        Ok(_) => line.trim_end_matches(['\r', '\n']).to_owned(),
+        Err(e) => fail(format!("fail to read password: {}", e)),
+    }
+}
+
+fn fail(message: impl Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -0,0 +1,491 @@
+//! A blocking counterpart of [`Session`](crate::session::Session).
+//!
+//! The auth methods in [`auth`](crate::auth) work with both sessions, going through
+//! the same steps, retried and rate limited the same way.
+//!
+//! [`CaptchaSolver`](crate::auth::CaptchaSolver)s are asynchronous, so the blocking
+//! session runs them on a runtime of the current thread, where timers and IO of tokio
+//! are available.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # fn doc() -> Result<(), neust::Error> {
+//! use neust::{auth, blocking::Session};
+//!
+//! let credential = auth::Credential::new("username", "password");
+//! let session = Session::new();
+//! let status = session.login(&credential)?;
+//! let username = status.get_username();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+
+use reqwest::blocking::{Client, ClientBuilder};
+
+#[cfg(feature = "webvpn")]
+use crate::endpoint::ENDPOINT_WEBVPN;
+use crate::endpoint::{Endpoint, ENDPOINT_DIRECT};
+use crate::error::Result;
+use crate::flow::{self, CheckStatus, Flow, Input, Logout, Step};
+use crate::jar::CookieJar;
+use crate::limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::status::UserStatus;
+use crate::transport::{self, BlockingTransport, HttpRequest, HttpResponse};
+
+/// An abstraction of auth method used in blocking [`Session`].
+///
+/// Requests sent by [`Session::execute`] and [`Session::execute_idempotent`] go through
+/// the [`RateLimiter`] and the [`RetryPolicy`] of the session, as the built-in methods do.
+///
+/// See also [`neust::AuthMethod`](crate::session::AuthMethod).
+pub trait AuthMethod {
+    /// Execute auth process.
+    fn execute(&self, session: &Session, endpoint: &Endpoint) -> Result<UserStatus>;
+}
+
+/// A blocking reqwest client with cookie management.
+///
+/// It must **not** be used inside an asynchronous runtime.
+///
+/// [`CaptchaSolver`](crate::auth::CaptchaSolver)s are run on a runtime of the current
+/// thread, see the [module documentation](self).
+///
+/// You do **not** have to wrap it in an [`Rc`] or [`Arc`] to **reuse** it,
+/// because it already uses an [`Arc`] internally.
+///
+/// [`Arc`]: std::sync::Arc
+/// [`Rc`]: std::rc::Rc
+#[derive(Debug, Clone)]
+pub struct Session {
+    client: Client,
+    transport: Arc<dyn BlockingTransport>,
+    cookie_jar: Arc<CookieJar>,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+impl Session {
+    /// Get [`Client`] to send requests.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Get internal [`CookieJar`].
+    ///
+    /// See also [`neust::Session::cookie_jar`](crate::session::Session::cookie_jar).
+    pub fn cookie_jar(&self) -> &CookieJar {
+        &self.cookie_jar
+    }
+
+    /// Get the [`RetryPolicy`] of idempotent steps in auth flows.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Get the [`RateLimiter`] consulted before requests of auth flows.
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Execute a request of auth flows, waiting for the [`RateLimiter`] if any.
+    ///
+    /// See also [`neust::Session::execute`](crate::session::Session::execute).
+    pub fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let (Some(limiter), Some(host)) = (&self.rate_limiter, request.url.host_str()) {
+            limiter.acquire_blocking(host);
+        }
+
+        self.transport.execute(request)
+    }
+
+    /// Drive `flow` to the end, performing its steps.
+    pub(crate) fn drive(&self, mut flow: impl Flow) -> Result<UserStatus> {
+        let mut input = Input::Start;
+
+        loop {
+            input = match flow.resume(input)? {
+                Step::Send {
+                    request,
+                    idempotent: true,
+                } => Input::Response(self.execute_idempotent(request)?),
+                Step::Send {
+                    request,
+                    idempotent: false,
+                } => Input::Response(self.execute(request)?),
+                Step::Solve { solver, challenge } => {
+                    Input::Answer(block_on(solver.0.solve(&challenge))?)
+                }
+                Step::Done(status) => return Ok(status),
+            }
+        }
+    }
+}
+
+/// Run `future` to the end on a runtime of the current thread.
+fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    Ok(runtime.block_on(future))
+}
+
+impl AsRef<Client> for Session {
+    fn as_ref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::with_client_builder(|b| b)
+    }
+}
+
+impl Session {
+    /// Creates a [`Session`] using default configurations.
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// Customize the client inside created [`Session`].
+    ///
+    /// Changes on [cookie_provider](crate::reqwest::blocking::ClientBuilder::cookie_provider),
+    /// [cookie_store](crate::reqwest::blocking::ClientBuilder::cookie_store) and
+    /// [redirect](crate::reqwest::blocking::ClientBuilder::redirect) will be ignored.
+    pub fn with_client_builder<B>(build: B) -> Self
+    where
+        B: FnOnce(ClientBuilder) -> ClientBuilder,
+    {
+        let cookie_jar = Arc::new(CookieJar::default());
+
+        let client = build(ClientBuilder::new().user_agent(flow::USER_AGENT))
+            .redirect(transport::redirect_policy())
+            .cookie_provider(cookie_jar.clone())
+            .build()
+            .expect("cannot initialize TLS backend, or cannot load the system configuration");
+
+        Session {
+            client: client.clone(),
+            transport: Arc::new(transport::ReqwestBlockingTransport { client }),
+            cookie_jar,
+            retry_policy: RetryPolicy::none(),
+            rate_limiter: None,
+        }
+    }
+
+    /// Retry idempotent steps in auth flows under `policy`.
+    ///
+    /// See also [`neust::Session::with_retry_policy`](crate::session::Session::with_retry_policy).
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Wait for `limiter` before requests of auth flows to the limited hosts.
+    ///
+    /// See also [`neust::Session::with_rate_limiter`](crate::session::Session::with_rate_limiter).
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Creates a [`Session`] sending requests of auth flows through a scripted transport.
+    #[cfg(test)]
+    pub(crate) fn with_transport(transport: Arc<crate::transport::mock::MockTransport>) -> Self {
+        let cookie_jar = transport.cookie_jar();
+
+        let client = ClientBuilder::new()
+            .cookie_provider(cookie_jar.clone())
+            .build()
+            .unwrap();
+
+        Session {
+            client,
+            transport,
+            cookie_jar,
+            retry_policy: RetryPolicy::none(),
+            rate_limiter: None,
+        }
+    }
+}
+
+impl Session {
+    /// Login to the CAS via a custom [`Endpoint`].
+    ///
+    /// See also [`neust::Session::login_at`](crate::session::Session::login_at).
+    pub fn login_at<A: AuthMethod>(&self, endpoint: &Endpoint, auth: &A) -> Result<UserStatus> {
+        auth.execute(self, endpoint)
+    }
+
+    /// Login to the CAS via a custom [`Endpoint`], replacing the user that already logged in.
+    ///
+    /// See also [`neust::Session::relogin_at`](crate::session::Session::relogin_at).
+    pub fn relogin_at<A: AuthMethod>(&self, endpoint: &Endpoint, auth: &A) -> Result<UserStatus> {
+        self.cookie_jar
+            .remove_cookies(endpoint.cookie_url(), endpoint.auth_cookie_names());
+
+        auth.execute(self, endpoint)
+    }
+
+    /// Check user status on the CAS via a custom [`Endpoint`] in the session.
+    ///
+    /// See also [`neust::Session::check_status_at`](crate::session::Session::check_status_at).
+    pub fn check_status_at(&self, endpoint: &Endpoint) -> Result<UserStatus> {
+        self.drive(CheckStatus::new(&self.cookie_jar, endpoint))
+    }
+
+    /// Logout from the CAS via a custom [`Endpoint`].
+    ///
+    /// See also [`neust::Session::logout_at`](crate::session::Session::logout_at).
+    pub fn logout_at(&self, endpoint: &Endpoint) -> Result<()> {
+        flow::ensure_logged_out(self.drive(Logout::new(&self.cookie_jar, endpoint))?)
+    }
+}
+
+impl Session {
+    /// Login to the CAS via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint).
+    ///
+    /// See also [`neust::Session::login`](crate::session::Session::login).
+    pub fn login<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
+        self.login_at(&ENDPOINT_DIRECT, auth)
+    }
+
+    /// Login to the CAS via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint),
+    /// replacing the user that already logged in.
+    ///
+    /// See also [`neust::Session::relogin`](crate::session::Session::relogin).
+    pub fn relogin<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
+        self.relogin_at(&ENDPOINT_DIRECT, auth)
+    }
+
+    /// Check user status on the CAS via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint)
+    /// in the session.
+    pub fn check_status(&self) -> Result<UserStatus> {
+        self.check_status_at(&ENDPOINT_DIRECT)
+    }
+
+    /// Logout from the CAS via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint)
+    /// and clear the auth-related cookies in the session.
+    ///
+    /// See also [`neust::Session::logout`](crate::session::Session::logout).
+    pub fn logout(&self) -> Result<()> {
+        self.logout_at(&ENDPOINT_DIRECT)
+    }
+}
+
+#[cfg(feature = "webvpn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "webvpn")))]
+impl Session {
+    /// Login to the CAS via [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint).
+    ///
+    /// See also [`neust::Session::login_via_webvpn`](crate::session::Session::login_via_webvpn).
+    pub fn login_via_webvpn<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
+        self.login_at(&ENDPOINT_WEBVPN, auth)
+    }
+
+    /// Login to the CAS via [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint),
+    /// replacing the user that already logged in via it.
+    ///
+    /// See also [`neust::Session::relogin_via_webvpn`](crate::session::Session::relogin_via_webvpn).
+    pub fn relogin_via_webvpn<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
+        self.relogin_at(&ENDPOINT_WEBVPN, auth)
+    }
+
+    /// Check user status on the CAS via
+    /// [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint) in the session.
+    pub fn check_status_via_webvpn(&self) -> Result<UserStatus> {
+        self.check_status_at(&ENDPOINT_WEBVPN)
+    }
+
+    /// Logout from the CAS via [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint)
+    /// and clear the auth-related cookies in the session.
+    ///
+    /// See also [`neust::Session::logout_via_webvpn`](crate::session::Session::logout_via_webvpn).
+    pub fn logout_via_webvpn(&self) -> Result<()> {
+        self.logout_at(&ENDPOINT_WEBVPN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use reqwest::{cookie::CookieStore, header::SET_COOKIE, Method, StatusCode, Url};
+
+    use crate::auth::{CaptchaChallenge, Credential};
+    use crate::blocking::Session;
+    use crate::error::Error;
+    use crate::limit::RateLimiter;
+    use crate::retry::RetryPolicy;
+    use crate::status::{RejectReason, UserStatus};
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    static LOGIN_URL: &str = "https://pass.neu.edu.cn/tpass/login";
+
+    fn login_page(extra: &str) -> String {
+        format!(
+            r#"<html><head><title>智慧东大--统一身份认证</title></head><body>
+<form id="loginForm" action="/tpass/login" method="post">
+<input type="hidden" id="lt" name="lt" value="LT-1-tpass"/>
+<input type="hidden" name="execution" value="e1s1"/>
+<input type="hidden" name="_eventId" value="submit"/>
+{}
+</form></body></html>"#,
+            extra
+        )
+    }
+
+    #[test]
+    fn test_login_retry_and_rate_limit() {
+        let portal = r#"<html><head><title>个人中心</title></head>
+<script>var id_number = "20180000";</script></html>"#;
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    LOGIN_URL,
+                    MockResponse::ok("").status(StatusCode::SERVICE_UNAVAILABLE),
+                )
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(login_page("")))
+                .expect(
+                    Method::POST,
+                    LOGIN_URL,
+                    MockResponse::redirect("https://portal.neu.edu.cn/tp_up/")
+                        .header(SET_COOKIE, "CASTGC=TGT-20180000-1-tpass; Path=/tpass/"),
+                )
+                .expect(
+                    Method::GET,
+                    "https://portal.neu.edu.cn/tp_up/",
+                    MockResponse::ok(portal),
+                )
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(portal)),
+        );
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let limiter = Arc::new(RateLimiter::new(10, Duration::from_millis(1)));
+        let session = Session::with_transport(transport.clone())
+            .with_retry_policy(policy)
+            .with_rate_limiter(limiter.clone());
+
+        let status = session
+            .login(&Credential::new("20180000", "password"))
+            .unwrap();
+        assert_eq!(status.get_username(), Some("20180000"));
+        assert_eq!(status.get_token(), Some("TGT-20180000-1-tpass"));
+        transport.assert_done();
+
+        // the portal is not limited.
+        assert_eq!(limiter.metrics().requests, 4);
+    }
+
+    #[test]
+    fn test_login_rejected() {
+        let error = r#"<span id="errormsg">用户名或密码错误</span>"#;
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(login_page("")))
+                .expect(Method::POST, LOGIN_URL, MockResponse::ok(login_page(error))),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        let status = session
+            .login(&Credential::new("20180000", "wrong"))
+            .unwrap();
+        assert_eq!(
+            status.get_reject_reason(),
+            Some(&RejectReason::WrongCredentials)
+        );
+        transport.assert_done();
+    }
+
+    #[test]
+    fn test_login_captcha() {
+        let captcha = r#"<input type="text" name="code"/><img id="codeImage" src="/tpass/code"/>"#;
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    LOGIN_URL,
+                    MockResponse::ok(login_page(captcha)),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/code",
+                    MockResponse::ok(vec![0xff, 0xd8]),
+                )
+                .expect(Method::POST, LOGIN_URL, MockResponse::ok(login_page("")))
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(login_page("")))
+                .expect(
+                    Method::GET,
+                    LOGIN_URL,
+                    MockResponse::ok(login_page(captcha)),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/code",
+                    MockResponse::ok(vec![0xff, 0xd8]),
+                ),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        // solvers may use timers of tokio.
+        let credential = Credential::new("20180000", "password").with_captcha_solver(
+            |_: CaptchaChallenge| async {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                Some("a1b2".to_owned())
+            },
+        );
+        let status = session.login(&credential).unwrap();
+        assert!(status.is_rejected());
+        let form = transport.requests()[2].form.clone().unwrap();
+        assert!(form.contains(&("code".to_owned(), "a1b2".to_owned())));
+
+        // the challenge is returned to the caller if the solver gives up.
+        let credential = Credential::new("20180000", "password")
+            .with_captcha_solver(|_: CaptchaChallenge| async { None });
+        let status = session.login(&credential).unwrap();
+        match status {
+            UserStatus::Rejected {
+                reason:
+                    Some(RejectReason::CaptchaRequired {
+                        challenge: Some(challenge),
+                    }),
+            } => assert_eq!(challenge.image(), [0xff, 0xd8]),
+            other => panic!("unexpected status: {:?}", other),
+        }
+        transport.assert_done();
+    }
+
+    #[test]
+    fn test_logout() {
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            "https://pass.neu.edu.cn/tpass/logout",
+            MockResponse::ok("").status(StatusCode::NOT_FOUND),
+        ));
+        let session = Session::with_transport(transport.clone());
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/").unwrap();
+        session
+            .cookie_jar()
+            .add_cookie_str("CASTGC=TGT-20180000-1-tpass; Path=/tpass/", &url);
+
+        assert!(matches!(
+            session.logout(),
+            Err(Error::HttpStatus {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+        transport.assert_done();
+        assert!(session.cookie_jar().cookies(&url).is_some());
+    }
+}
@@ -0,0 +1,283 @@
+//! Provide helpers for CAS service tickets.
+//!
+//! Services connected to the CAS validate service tickets issued to users
+//! to learn who they are. See also [`Session::service_ticket`].
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::{header::LOCATION, Client, Url};
+
+use crate::endpoint::{Endpoint, ENDPOINT_DIRECT};
+use crate::error::{Error, Result};
+use crate::session::Session;
+use crate::transport::HttpRequest;
+
+/// The result of a successful service ticket validation.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ServiceValidation {
+    /// The username of the user the ticket was issued to.
+    pub username: String,
+    /// The attributes released to the service, only available in CAS protocol 3.0.
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+/// Validate `ticket` issued for `service` on the CAS via
+/// [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint) using `client`.
+///
+/// Returns [`Error::TicketValidationError`] if the CAS rejects the ticket,
+/// or [`Error::HttpStatus`] if the CAS does not respond with a success status.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn doc() -> Result<(), neust::Error> {
+/// # use neust::cas;
+/// let client = neust::reqwest::Client::new();
+/// let validation = cas::validate(&client, "https://example.com/", "ST-xxxx-tpass").await?;
+/// println!("{}", validation.username);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn validate(client: &Client, service: &str, ticket: &str) -> Result<ServiceValidation> {
+    validate_at(client, &ENDPOINT_DIRECT, service, ticket).await
+}
+
+/// Validate `ticket` issued for `service` on the CAS via a custom [`Endpoint`] using `client`.
+///
+/// Behaves the same as [`validate`] except for the endpoint.
+pub async fn validate_at(
+    client: &Client,
+    endpoint: &Endpoint,
+    service: &str,
+    ticket: &str,
+) -> Result<ServiceValidation> {
+    let url = Url::parse_with_params(
+        endpoint.service_validate_url(),
+        &[("service", service), ("ticket", ticket)],
+    )
+    .map_err(|_| Error::invalid_url(endpoint.service_validate_url()))?;
+
+    let response = client.get(url).send().await?;
+    crate::flow::ensure_success(response.url(), response.status())?;
+    let body = response.text().await?;
+
+    parse_service_response(&body)
+        .ok_or_else(|| Error::parse_page_error(endpoint.service_validate_url()))?
+}
+
+/// Parse the XML response of `serviceValidate` in CAS protocol 2.0 or 3.0.
+///
+/// Returns [`None`] if the response is neither a success nor a failure.
+fn parse_service_response(xml: &str) -> Option<Result<ServiceValidation>> {
+    lazy_static! {
+        static ref USER_RE: Regex =
+            Regex::new(r"(?s)<cas:authenticationSuccess>.*?<cas:user>\s*(.*?)\s*</cas:user>")
+                .unwrap();
+        static ref ATTRIBUTES_RE: Regex =
+            Regex::new(r"(?s)<cas:attributes>(.*?)</cas:attributes>").unwrap();
+        static ref ATTRIBUTE_RE: Regex =
+            Regex::new(r"(?s)<cas:([\w.-]+)>\s*(.*?)\s*</cas:([\w.-]+)>").unwrap();
+        static ref FAILURE_RE: Regex = Regex::new(
+            r#"(?s)<cas:authenticationFailure\s+code="(.*?)"\s*>\s*(.*?)\s*</cas:authenticationFailure>"#
+        )
+        .unwrap();
+    }
+
+    if let Some(cap) = FAILURE_RE.captures(xml) {
+        return Some(Err(Error::TicketValidationError {
+            code: unescape(&cap[1]),
+            message: unescape(&cap[2]),
+        }));
+    }
+
+    let username = unescape(&USER_RE.captures(xml)?[1]);
+
+    let mut attributes = HashMap::<String, Vec<String>>::new();
+    if let Some(cap) = ATTRIBUTES_RE.captures(xml) {
+        for attr in ATTRIBUTE_RE.captures_iter(&cap[1]) {
+            if attr[1] == attr[3] {
+                attributes
+                    .entry(attr[1].to_owned())
+                    .or_default()
+                    .push(unescape(&attr[2]));
+            }
+        }
+    }
+
+    Some(Ok(ServiceValidation {
+        username,
+        attributes,
+    }))
+}
+
+fn unescape(raw: &str) -> String {
+    raw.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Find the service ticket in the url that the CAS redirects to.
+fn find_ticket(url: &Url) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, v)| k == "ticket" && v.starts_with("ST-"))
+        .map(|(_, v)| v.into_owned())
+}
+
+impl Session {
+    /// Acquire a service ticket for `service` from the CAS via
+    /// [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint).
+    ///
+    /// The redirect to `service` is not followed, so the ticket is left unused and
+    /// can be validated by the service itself, or by [`validate`].
+    ///
+    /// Returns [`Error::StatusConflict`] if
+    /// no user has logged in.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::{auth, cas, Session};
+    /// let session = Session::new();
+    /// session.login(&auth::Credential::new("username", "password")).await?;
+    /// let ticket = session.service_ticket("https://example.com/").await?;
+    /// let validation = cas::validate(session.client(), "https://example.com/", &ticket).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn service_ticket(&self, service: &str) -> Result<String> {
+        self.service_ticket_at(&ENDPOINT_DIRECT, service).await
+    }
+
+    /// Acquire a service ticket for `service` from the CAS via a custom [`Endpoint`].
+    ///
+    /// Behaves the same as [`Session::service_ticket`] except for the endpoint.
+    pub async fn service_ticket_at(&self, endpoint: &Endpoint, service: &str) -> Result<String> {
+        let url = Url::parse_with_params(endpoint.login_url(), &[("service", service)])
+            .map_err(|_| Error::invalid_url(endpoint.login_url()))?;
+
+        let response = self
+            .execute(HttpRequest::get(url.clone()).no_redirect())
+            .await?;
+
+        let location = match response.headers.contains_key(LOCATION) {
+            true => response.location(),
+            false => return Err(Error::StatusConflict),
+        };
+
+        location
+            .as_ref()
+            .and_then(find_ticket)
+            .ok_or_else(|| Error::parse_page_error(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use reqwest::{Method, Url};
+
+    use crate::cas::{find_ticket, parse_service_response};
+    use crate::error::Error;
+    use crate::session::Session;
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    #[tokio::test]
+    async fn test_service_ticket() {
+        let login_url = "https://pass.neu.edu.cn/tpass/login?service=https%3A%2F%2Fexample.com%2F";
+
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            login_url,
+            MockResponse::redirect("https://example.com/?ticket=ST-1-tpass"),
+        ));
+        let session = Session::with_transport(transport.clone());
+
+        let ticket = session.service_ticket("https://example.com/").await;
+        assert_eq!(ticket.unwrap(), "ST-1-tpass");
+        transport.assert_done();
+
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            login_url,
+            MockResponse::ok("<html></html>"),
+        ));
+        let session = Session::with_transport(transport.clone());
+
+        assert!(matches!(
+            session.service_ticket("https://example.com/").await,
+            Err(Error::StatusConflict)
+        ));
+        transport.assert_done();
+    }
+
+    #[test]
+    fn test_parse_service_response() {
+        let success_v2 = r#"<cas:serviceResponse xmlns:cas="http://www.yale.edu/tp/cas">
+    <cas:authenticationSuccess>
+        <cas:user>20180000</cas:user>
+    </cas:authenticationSuccess>
+</cas:serviceResponse>"#;
+        let validation = parse_service_response(success_v2).unwrap().unwrap();
+        assert_eq!(validation.username, "20180000");
+        assert!(validation.attributes.is_empty());
+
+        let success_v3 = r#"<cas:serviceResponse xmlns:cas="http://www.yale.edu/tp/cas">
+    <cas:authenticationSuccess>
+        <cas:user>20180000</cas:user>
+        <cas:attributes>
+            <cas:name>Zhang &amp; San</cas:name>
+            <cas:group>student</cas:group>
+            <cas:group>member</cas:group>
+        </cas:attributes>
+    </cas:authenticationSuccess>
+</cas:serviceResponse>"#;
+        let validation = parse_service_response(success_v3).unwrap().unwrap();
+        assert_eq!(validation.username, "20180000");
+        assert_eq!(validation.attributes["name"], ["Zhang & San"]);
+        assert_eq!(validation.attributes["group"], ["student", "member"]);
+
+        let failure = r#"<cas:serviceResponse xmlns:cas="http://www.yale.edu/tp/cas">
+    <cas:authenticationFailure code="INVALID_TICKET">
+        Ticket ST-1856339-aA5Yuvrxzpv8Tau1cYQ7 not recognized
+    </cas:authenticationFailure>
+</cas:serviceResponse>"#;
+        match parse_service_response(failure).unwrap() {
+            Err(Error::TicketValidationError { code, message }) => {
+                assert_eq!(code, "INVALID_TICKET");
+                assert_eq!(
+                    message,
+                    "Ticket ST-1856339-aA5Yuvrxzpv8Tau1cYQ7 not recognized"
+                );
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        assert!(parse_service_response("<html></html>").is_none());
+    }
+
+    #[test]
+    fn test_find_ticket() {
+        let table = vec![
+            (
+                "https://example.com/?ticket=ST-1-abc-tpass",
+                Some("ST-1-abc-tpass".to_owned()),
+            ),
+            (
+                "https://example.com/login?a=1&ticket=ST-2-tpass",
+                Some("ST-2-tpass".to_owned()),
+            ),
+            ("https://example.com/?ticket=", None),
+            ("https://example.com/", None),
+        ];
+
+        for (url, expected) in table {
+            assert_eq!(find_ticket(&Url::parse(url).unwrap()), expected)
+        }
+    }
+}
@@ -1,6 +1,9 @@
 //! Currently the CAS has two endpoint: [`DirectEndpoint`] and [`WebVPNEndpoint`].
 //!
 //! To use [`WebVPNEndpoint`], please enable feature **webvpn**.
+//!
+//! Other deployments of the CAS can be accessed through a custom [`Endpoint`](crate::Endpoint)
+//! and the `*_at` methods of [`Session`](crate::Session), e.g. [`Session::login_at`](crate::Session::login_at).
 #![allow(missing_debug_implementations)]
 
 /// Access CAS directly.
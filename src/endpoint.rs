@@ -1,29 +1,275 @@
 use lazy_static::lazy_static;
 use reqwest::Url;
 
-#[derive(Debug)]
+use crate::error::{Error, Result};
+
+/// A CAS deployment that a [`Session`](crate::session::Session) logs into.
+///
+/// Besides the built-in [`DirectEndpoint`] and [`WebVPNEndpoint`], custom endpoints
+/// can be created by [`Endpoint::builder`] to access a staging mirror, a local mock CAS
+/// or another deployment running the same software.
+///
+/// See also [documentation for endpoints](crate::doc::endpoint).
+///
+/// # Examples
+///
+/// ```
+/// # use neust::Endpoint;
+/// let endpoint = Endpoint::builder("http://127.0.0.1:8080/tpass/").build().unwrap();
+/// assert_eq!(endpoint.login_url(), "http://127.0.0.1:8080/tpass/login");
+/// assert_eq!(endpoint.cookie_name(), "CASTGC");
+/// ```
+///
+/// [`DirectEndpoint`]: crate::doc::endpoint::DirectEndpoint
+/// [`WebVPNEndpoint`]: crate::doc::endpoint::WebVPNEndpoint
+#[derive(Debug, Clone)]
 pub struct Endpoint {
-    pub login_url: &'static str,
-    pub cookie_name: &'static str,
-    pub wechat_verify_url: &'static str,
-    pub cookie_url: Url,
+    login_url: String,
+    logout_url: String,
+    service_validate_url: String,
+    wechat_verify_url: String,
+    cookie_name: String,
+    auth_cookie_names: Vec<String>,
+    cookie_url: Url,
+}
+
+impl Endpoint {
+    /// Creates an [`EndpointBuilder`] for the CAS located at `base_url`,
+    /// e.g. `https://pass.neu.edu.cn/tpass/`.
+    pub fn builder(base_url: impl Into<String>) -> EndpointBuilder {
+        EndpointBuilder {
+            base_url: base_url.into(),
+            login_url: None,
+            logout_url: None,
+            service_validate_url: None,
+            wechat_verify_url: None,
+            cookie_name: None,
+            auth_cookie_names: None,
+            cookie_url: None,
+        }
+    }
+
+    /// Get the [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint).
+    pub fn direct() -> &'static Endpoint {
+        &ENDPOINT_DIRECT
+    }
+
+    /// Get the [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint).
+    #[cfg(feature = "webvpn")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "webvpn")))]
+    pub fn webvpn() -> &'static Endpoint {
+        &ENDPOINT_WEBVPN
+    }
+
+    /// Get the url of the login page.
+    pub fn login_url(&self) -> &str {
+        &self.login_url
+    }
+
+    /// Get the url to logout.
+    pub fn logout_url(&self) -> &str {
+        &self.logout_url
+    }
+
+    /// Get the url to validate service tickets.
+    pub fn service_validate_url(&self) -> &str {
+        &self.service_validate_url
+    }
+
+    /// Get the url to check whether Wechat has authorized the login request.
+    pub fn wechat_verify_url(&self) -> &str {
+        &self.wechat_verify_url
+    }
+
+    /// Get the name of the cookie holding the endpoint-specific token.
+    pub fn cookie_name(&self) -> &str {
+        &self.cookie_name
+    }
+
+    /// Get the names of cookies removed on logout or relogin.
+    pub fn auth_cookie_names(&self) -> &[String] {
+        &self.auth_cookie_names
+    }
+
+    /// Get the url that the auth-related cookies belong to.
+    pub fn cookie_url(&self) -> &Url {
+        &self.cookie_url
+    }
+}
+
+/// A builder to create [`Endpoint`].
+///
+/// Urls not specified are resolved against the base url, with the same layout as
+/// [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint).
+#[derive(Debug, Clone)]
+pub struct EndpointBuilder {
+    base_url: String,
+    login_url: Option<String>,
+    logout_url: Option<String>,
+    service_validate_url: Option<String>,
+    wechat_verify_url: Option<String>,
+    cookie_name: Option<String>,
+    auth_cookie_names: Option<Vec<String>>,
+    cookie_url: Option<String>,
+}
+
+impl EndpointBuilder {
+    /// Set the url of the login page. Defaults to `{base_url}login`.
+    pub fn login_url(mut self, url: impl Into<String>) -> Self {
+        self.login_url = Some(url.into());
+        self
+    }
+
+    /// Set the url to logout. Defaults to `{base_url}logout`.
+    pub fn logout_url(mut self, url: impl Into<String>) -> Self {
+        self.logout_url = Some(url.into());
+        self
+    }
+
+    /// Set the url to validate service tickets. Defaults to `{base_url}serviceValidate`.
+    ///
+    /// Use `{base_url}p3/serviceValidate` to validate via CAS protocol 3.0.
+    pub fn service_validate_url(mut self, url: impl Into<String>) -> Self {
+        self.service_validate_url = Some(url.into());
+        self
+    }
+
+    /// Set the url to check Wechat authorization. Defaults to `{base_url}checkQRCodeScan`.
+    pub fn wechat_verify_url(mut self, url: impl Into<String>) -> Self {
+        self.wechat_verify_url = Some(url.into());
+        self
+    }
+
+    /// Set the name of the cookie holding the token. Defaults to `CASTGC`.
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.cookie_name = Some(name.into());
+        self
+    }
+
+    /// Set the names of cookies removed on logout or relogin.
+    /// Defaults to the cookie name and `jsessionid_tpass`.
+    pub fn auth_cookie_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.auth_cookie_names = Some(names.into_iter().map(|s| s.into()).collect());
+        self
+    }
+
+    /// Set the url that the auth-related cookies belong to. Defaults to the base url.
+    pub fn cookie_url(mut self, url: impl Into<String>) -> Self {
+        self.cookie_url = Some(url.into());
+        self
+    }
+
+    /// Creates the [`Endpoint`].
+    ///
+    /// Returns [`Error::InvalidUrl`](crate::error::Error::InvalidUrl) if any url is invalid.
+    pub fn build(self) -> Result<Endpoint> {
+        let mut base_url = self.base_url;
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        let base = parse_url(&base_url)?;
+
+        let resolve = |url: Option<String>, path: &str| -> Result<String> {
+            match url {
+                Some(url) => parse_url(&url).map(|u| u.into()),
+                None => Ok(base
+                    .join(path)
+                    .map_err(|_| Error::invalid_url(path))?
+                    .into()),
+            }
+        };
+
+        let cookie_name = self.cookie_name.unwrap_or_else(|| "CASTGC".to_owned());
+
+        Ok(Endpoint {
+            login_url: resolve(self.login_url, "login")?,
+            logout_url: resolve(self.logout_url, "logout")?,
+            service_validate_url: resolve(self.service_validate_url, "serviceValidate")?,
+            wechat_verify_url: resolve(self.wechat_verify_url, "checkQRCodeScan")?,
+            auth_cookie_names: self
+                .auth_cookie_names
+                .unwrap_or_else(|| vec![cookie_name.clone(), "jsessionid_tpass".to_owned()]),
+            cookie_name,
+            cookie_url: match self.cookie_url {
+                Some(url) => parse_url(&url)?,
+                None => base,
+            },
+        })
+    }
+}
+
+pub(crate) fn parse_url(url: &str) -> Result<Url> {
+    Url::parse(url).map_err(|_| Error::invalid_url(url))
 }
 
 lazy_static! {
-    pub(crate) static ref ENDPOINT_DIRECT: Endpoint = Endpoint {
-        login_url: "https://pass.neu.edu.cn/tpass/login",
-        cookie_name: "CASTGC",
-        wechat_verify_url: "https://pass.neu.edu.cn/tpass/checkQRCodeScan",
-        cookie_url: Url::parse("https://pass.neu.edu.cn/tpass/").unwrap(),
-    };
+    pub(crate) static ref ENDPOINT_DIRECT: Endpoint =
+        Endpoint::builder("https://pass.neu.edu.cn/tpass/")
+            .build()
+            .unwrap();
 }
 
 #[cfg(feature = "webvpn")]
 lazy_static! {
-    pub(crate) static ref ENDPOINT_WEBVPN: Endpoint  = Endpoint {
-        login_url: "https://webvpn.neu.edu.cn/https/77726476706e69737468656265737421e0f6528f693e6d45300d8db9d6562d/tpass/login",
-        cookie_name: "wengine_vpn_ticketwebvpn_neu_edu_cn",
-        wechat_verify_url: "https://webvpn.neu.edu.cn/https/77726476706e69737468656265737421e0f6528f693e6d45300d8db9d6562d/tpass/checkQRCodeScan",
-        cookie_url: Url::parse("https://webvpn.neu.edu.cn/").unwrap(),
-    };
+    pub(crate) static ref ENDPOINT_WEBVPN: Endpoint = crate::webvpn::WEBVPN_NEU.endpoint().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endpoint::{Endpoint, ENDPOINT_DIRECT};
+
+    #[test]
+    fn test_endpoint_direct() {
+        let endpoint = &*ENDPOINT_DIRECT;
+        assert_eq!(endpoint.login_url(), "https://pass.neu.edu.cn/tpass/login");
+        assert_eq!(
+            endpoint.logout_url(),
+            "https://pass.neu.edu.cn/tpass/logout"
+        );
+        assert_eq!(
+            endpoint.service_validate_url(),
+            "https://pass.neu.edu.cn/tpass/serviceValidate"
+        );
+        assert_eq!(
+            endpoint.wechat_verify_url(),
+            "https://pass.neu.edu.cn/tpass/checkQRCodeScan"
+        );
+        assert_eq!(endpoint.cookie_name(), "CASTGC");
+        assert_eq!(endpoint.auth_cookie_names(), ["CASTGC", "jsessionid_tpass"]);
+        assert_eq!(
+            endpoint.cookie_url().as_str(),
+            "https://pass.neu.edu.cn/tpass/"
+        );
+    }
+
+    #[cfg(feature = "webvpn")]
+    #[test]
+    fn test_endpoint_webvpn() {
+        let endpoint = Endpoint::webvpn();
+        assert_eq!(endpoint.login_url(), "https://webvpn.neu.edu.cn/https/77726476706e69737468656265737421e0f6528f693e6d45300d8db9d6562d/tpass/login");
+        assert_eq!(endpoint.wechat_verify_url(), "https://webvpn.neu.edu.cn/https/77726476706e69737468656265737421e0f6528f693e6d45300d8db9d6562d/tpass/checkQRCodeScan");
+        assert_eq!(endpoint.cookie_url().as_str(), "https://webvpn.neu.edu.cn/");
+    }
+
+    #[test]
+    fn test_endpoint_builder() {
+        let endpoint = Endpoint::builder("http://127.0.0.1:8080/tpass")
+            .login_url("http://127.0.0.1:8080/cas/login")
+            .cookie_name("TGC")
+            .build()
+            .unwrap();
+        assert_eq!(endpoint.login_url(), "http://127.0.0.1:8080/cas/login");
+        assert_eq!(endpoint.logout_url(), "http://127.0.0.1:8080/tpass/logout");
+        assert_eq!(endpoint.auth_cookie_names(), ["TGC", "jsessionid_tpass"]);
+
+        assert!(Endpoint::builder("not a url").build().is_err());
+        assert!(Endpoint::builder("http://127.0.0.1/")
+            .cookie_url("::")
+            .build()
+            .is_err());
+    }
 }
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::status::UserStatus;
+
 /// Errors may occur during session operations.
 #[non_exhaustive]
 #[derive(Error, Debug)]
@@ -10,6 +12,9 @@ pub enum Error {
     /// - login via [`DirectEndpoint`] in sessions that already have logged-in user via [`DirectEndpoint`]
     /// - login via [`WebVPNEndpoint`] in sessions that have no logged-in user via [`DirectEndpoint`]
     /// - login via [`WebVPNEndpoint`] in sessions that already have logged-in user via [`WebVPNEndpoint`]
+    /// - the user is still online after logout
+    /// - acquire service tickets in sessions that have no logged-in user
+    /// - reset the password of a user who does not need to
     /// - the page redirect behavior is changed
     ///
     /// See also [documentation for endpoints](crate::doc::endpoint).
@@ -31,16 +36,145 @@ pub enum Error {
         url: String,
     },
 
+    /// Errors occur when the login form of the CAS lacks fields required to log in.
+    ///
+    /// Common causes:
+    /// - CAS service is upgraded
+    /// - the structure of the login page is changed
+    #[error("missing fields {fields:?} in the login form of url {url}")]
+    MissingFormFields {
+        /// The url of the login page
+        url: String,
+        /// Names of the missing fields
+        fields: Vec<String>,
+    },
+
+    /// Errors occur when a new password violates the
+    /// [`PasswordPolicy`](crate::reset::PasswordPolicy) before resetting.
+    #[error("password violates the policy: {violation}")]
+    PasswordPolicy {
+        /// The violated rule
+        violation: crate::reset::PolicyViolation,
+    },
+
+    /// Errors occur when the CAS refuses to reset the password, e.g. the old password is wrong.
+    #[error("fail to reset password: {message}")]
+    ResetRejected {
+        /// The error message shown by the CAS
+        message: String,
+    },
+
+    /// Errors occur when a [`SessionManager`](crate::manager::SessionManager) finds the user
+    /// online but not active, e.g. [`UserStatus::NeedReset`] or [`UserStatus::Banned`],
+    /// which logging in again can not fix.
+    #[error("user is not active, status: {status}")]
+    UserInactive {
+        /// The status checked on the CAS
+        status: UserStatus,
+    },
+
+    /// Errors occur when a [`SessionManager`](crate::manager::SessionManager) can not
+    /// get an active user after logging in again.
+    #[error("fail to reauthenticate, status: {status}")]
+    ReauthFailed {
+        /// The status after logging in again
+        status: UserStatus,
+    },
+
+    /// Errors occur when the CAS rejects a service ticket.
+    #[error("fail to validate service ticket: {code} {message}")]
+    TicketValidationError {
+        /// The error code from the CAS, e.g. `INVALID_TICKET`
+        code: String,
+        /// The error message from the CAS
+        message: String,
+    },
+
+    /// Errors occur when accessing a service ends at the login page instead of the service.
+    ///
+    /// Common causes:
+    /// - no user has logged in via the endpoint
+    /// - the user is not allowed to access the service
+    #[error("access to {url} is rejected")]
+    AccessRejected {
+        /// The url of the service
+        url: String,
+    },
+
+    /// Errors occur when the CAS responds with an unexpected status.
+    ///
+    /// Common causes:
+    /// - the CAS keeps responding with one of
+    ///   [`RetryPolicy::retryable_statuses`](crate::retry::RetryPolicy::retryable_statuses),
+    ///   e.g. `503 Service Unavailable` at peak hours
+    /// - the CAS fails to logout
+    #[error("{url} responded with status {status}")]
+    HttpStatus {
+        /// The url of the request
+        url: String,
+        /// The status of the response
+        status: reqwest::StatusCode,
+    },
+
+    /// Errors occur when accessing a service redirects too many times.
+    #[error("too many redirects when accessing {url}")]
+    TooManyRedirects {
+        /// The url of the service
+        url: String,
+    },
+
+    /// Errors caused by malformed urls.
+    #[error("invalid url {url}")]
+    InvalidUrl {
+        /// The invalid url
+        url: String,
+    },
+
+    /// Errors caused by urls that are not WebVPN urls of services.
+    #[error("invalid webvpn url {url}")]
+    InvalidWebVpnUrl {
+        /// The invalid url
+        url: String,
+    },
+
+    /// Errors caused when a [`SessionSnapshot`](crate::snapshot::SessionSnapshot)
+    /// can not be serialized or deserialized.
+    #[error("invalid session snapshot: {reason}")]
+    SnapshotError {
+        /// The reason why the snapshot is invalid
+        reason: String,
+    },
+
     /// Errors from reqwest layer.
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
+
+    /// Errors from io layer.
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
 }
 
-/// A `Result` alias where the `Err` case is [`neust::Error`](crate::error::Error).
+/// A `Result` alias where the `Err` case is [`neust::Error`](enum@crate::error::Error).
 pub type Result<T> = std::result::Result<T, Error>;
 
 impl Error {
     pub(crate) fn parse_page_error(url: impl Into<String>) -> Self {
         Error::ParsePageError { url: url.into() }
     }
+
+    pub(crate) fn invalid_url(url: impl Into<String>) -> Self {
+        Error::InvalidUrl { url: url.into() }
+    }
+
+    #[cfg(feature = "webvpn")]
+    pub(crate) fn invalid_webvpn_url(url: impl Into<String>) -> Self {
+        Error::InvalidWebVpnUrl { url: url.into() }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn snapshot_error(reason: impl ToString) -> Self {
+        Error::SnapshotError {
+            reason: reason.to_string(),
+        }
+    }
 }
@@ -0,0 +1,418 @@
+//! Steps of auth flows shared by the asynchronous and the blocking `Session`,
+//! so that both stay in step.
+//!
+//! Each flow is a state machine that never sends requests itself. A session drives it
+//! by performing the [`Step`]s it asks for, and resuming it with the outcome.
+
+use std::mem;
+
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{cookie::CookieStore, StatusCode, Url};
+
+use crate::auth::captcha::{CaptchaChallenge, SharedSolver};
+use crate::auth::credential::LoginPage;
+#[cfg(feature = "wechat")]
+use crate::auth::{wechat::is_authorized, Wechat};
+use crate::auth::{Credential, Token};
+use crate::endpoint::{parse_url, Endpoint};
+use crate::error::{Error, Result};
+use crate::jar::CookieJar;
+use crate::status::{RejectReason, UserStatus};
+use crate::transport::{HttpRequest, HttpResponse};
+
+pub(crate) static USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// What a [`Flow`] asks the session to do next.
+pub(crate) enum Step {
+    /// Send `request`, then resume the flow with [`Input::Response`].
+    ///
+    /// Idempotent requests are retried under the
+    /// [`RetryPolicy`](crate::retry::RetryPolicy) of the session.
+    Send {
+        request: HttpRequest,
+        idempotent: bool,
+    },
+    /// Ask `solver` to answer `challenge`, then resume the flow with [`Input::Answer`].
+    Solve {
+        solver: SharedSolver,
+        challenge: CaptchaChallenge,
+    },
+    /// The flow finished with the status.
+    Done(UserStatus),
+}
+
+impl Step {
+    fn send(request: HttpRequest) -> Step {
+        Step::Send {
+            request,
+            idempotent: false,
+        }
+    }
+
+    fn send_idempotent(request: HttpRequest) -> Step {
+        Step::Send {
+            request,
+            idempotent: true,
+        }
+    }
+}
+
+/// What a [`Flow`] is resumed with.
+pub(crate) enum Input {
+    /// Starts the flow.
+    Start,
+    /// The response to [`Step::Send`].
+    Response(HttpResponse),
+    /// The answer to [`Step::Solve`], [`None`] if the solver gives up.
+    Answer(Option<String>),
+}
+
+/// An auth flow driven by a session.
+pub(crate) trait Flow {
+    /// Resume the flow with `input`, returns the next step.
+    ///
+    /// It's first resumed with [`Input::Start`], and never resumed after
+    /// [`Step::Done`] or an error.
+    fn resume(&mut self, input: Input) -> Result<Step>;
+}
+
+/// Request the login page of `endpoint`, which tells the status.
+fn check_status(endpoint: &Endpoint) -> Result<Step> {
+    Ok(Step::send_idempotent(HttpRequest::get(parse_url(
+        endpoint.login_url(),
+    )?)))
+}
+
+fn unexpected(flow: &str) -> ! {
+    unreachable!("{} is resumed with unexpected input", flow)
+}
+
+/// Checks the status on the login page of the endpoint.
+pub(crate) struct CheckStatus<'a> {
+    cookie_jar: &'a CookieJar,
+    endpoint: &'a Endpoint,
+}
+
+impl<'a> CheckStatus<'a> {
+    pub(crate) fn new(cookie_jar: &'a CookieJar, endpoint: &'a Endpoint) -> Self {
+        CheckStatus {
+            cookie_jar,
+            endpoint,
+        }
+    }
+}
+
+impl Flow for CheckStatus<'_> {
+    fn resume(&mut self, input: Input) -> Result<Step> {
+        match input {
+            Input::Start => check_status(self.endpoint),
+            Input::Response(response) => Ok(Step::Done(status_from_page(
+                self.cookie_jar,
+                self.endpoint,
+                &response.text(),
+            ))),
+            Input::Answer(_) => unexpected("status check"),
+        }
+    }
+}
+
+/// Logs out from the endpoint, finishing with the status checked after logout,
+/// which should be confirmed by [`ensure_logged_out`].
+pub(crate) struct Logout<'a> {
+    check: CheckStatus<'a>,
+    logged_out: bool,
+}
+
+impl<'a> Logout<'a> {
+    pub(crate) fn new(cookie_jar: &'a CookieJar, endpoint: &'a Endpoint) -> Self {
+        Logout {
+            check: CheckStatus::new(cookie_jar, endpoint),
+            logged_out: false,
+        }
+    }
+}
+
+impl Flow for Logout<'_> {
+    fn resume(&mut self, input: Input) -> Result<Step> {
+        let endpoint = self.check.endpoint;
+
+        match (self.logged_out, input) {
+            (false, Input::Start) => Ok(Step::send_idempotent(HttpRequest::get(parse_url(
+                endpoint.logout_url(),
+            )?))),
+            (false, Input::Response(response)) => {
+                ensure_success(&response.url, response.status)?;
+                self.check
+                    .cookie_jar
+                    .remove_cookies(endpoint.cookie_url(), endpoint.auth_cookie_names());
+                self.logged_out = true;
+                check_status(endpoint)
+            }
+            (true, input @ Input::Response(_)) => self.check.resume(input),
+            _ => unexpected("logout"),
+        }
+    }
+}
+
+/// Logs in with a [`Credential`] through the login form of the endpoint.
+pub(crate) struct CredentialLogin<'a> {
+    credential: &'a Credential,
+    check: CheckStatus<'a>,
+    state: CredentialState,
+}
+
+enum CredentialState {
+    Start,
+    /// Waiting for the login page.
+    LoginPage,
+    /// Waiting for the captcha image of the login page.
+    CaptchaImage(LoginPage),
+    /// Waiting for the solver to answer the captcha.
+    Solving(LoginPage, CaptchaChallenge),
+    /// Waiting for the response to the login form.
+    Submitted,
+    /// Waiting for the status after login.
+    Checking,
+}
+
+impl<'a> CredentialLogin<'a> {
+    pub(crate) fn new(
+        credential: &'a Credential,
+        cookie_jar: &'a CookieJar,
+        endpoint: &'a Endpoint,
+    ) -> Self {
+        CredentialLogin {
+            credential,
+            check: CheckStatus::new(cookie_jar, endpoint),
+            state: CredentialState::Start,
+        }
+    }
+
+    /// Submit the login form, the credential form is never retried.
+    fn submit(&mut self, page: &LoginPage, captcha_answer: Option<&str>) -> Result<Step> {
+        self.state = CredentialState::Submitted;
+        Ok(Step::send(HttpRequest::post_form(
+            page.form.action.clone(),
+            self.credential.build_login_form(page, captcha_answer),
+        )))
+    }
+}
+
+impl Flow for CredentialLogin<'_> {
+    fn resume(&mut self, input: Input) -> Result<Step> {
+        let endpoint = self.check.endpoint;
+
+        match (mem::replace(&mut self.state, CredentialState::Start), input) {
+            (CredentialState::Start, Input::Start) => {
+                self.state = CredentialState::LoginPage;
+                check_status(endpoint)
+            }
+            (CredentialState::LoginPage, Input::Response(response)) => {
+                let page = LoginPage::parse(endpoint, &response.url, &response.text())?;
+
+                match (&page.captcha, self.credential.captcha_answer()) {
+                    (Some(_), Some(answer)) => self.submit(&page, Some(answer)),
+                    (Some(captcha), None) => {
+                        let request = HttpRequest::get(captcha.image_url.clone());
+                        self.state = CredentialState::CaptchaImage(page);
+                        Ok(Step::send(request))
+                    }
+                    (None, _) => self.submit(&page, None),
+                }
+            }
+            (CredentialState::CaptchaImage(page), Input::Response(response)) => {
+                let content_type = response
+                    .headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.to_owned());
+                let challenge = CaptchaChallenge::new(response.body, content_type);
+
+                match self.credential.captcha_solver() {
+                    Some(solver) => {
+                        self.state = CredentialState::Solving(page, challenge.clone());
+                        Ok(Step::Solve {
+                            solver: solver.clone(),
+                            challenge,
+                        })
+                    }
+                    None => Ok(Step::Done(captcha_required(challenge))),
+                }
+            }
+            (CredentialState::Solving(page, challenge), Input::Answer(answer)) => match answer {
+                Some(answer) => self.submit(&page, Some(&answer)),
+                None => Ok(Step::Done(captcha_required(challenge))),
+            },
+            (CredentialState::Submitted, Input::Response(response)) => {
+                match rejected_from_page(&response.text()) {
+                    Some(status) => Ok(Step::Done(status)),
+                    None => {
+                        self.state = CredentialState::Checking;
+                        check_status(endpoint)
+                    }
+                }
+            }
+            (CredentialState::Checking, input @ Input::Response(_)) => self.check.resume(input),
+            _ => unexpected("credential login"),
+        }
+    }
+}
+
+fn captcha_required(challenge: CaptchaChallenge) -> UserStatus {
+    UserStatus::Rejected {
+        reason: Some(RejectReason::CaptchaRequired {
+            challenge: Some(challenge),
+        }),
+    }
+}
+
+/// Logs in with a [`Token`] by storing it in the cookie jar.
+pub(crate) struct TokenLogin<'a> {
+    token: &'a Token,
+    check: CheckStatus<'a>,
+}
+
+impl<'a> TokenLogin<'a> {
+    pub(crate) fn new(token: &'a Token, cookie_jar: &'a CookieJar, endpoint: &'a Endpoint) -> Self {
+        TokenLogin {
+            token,
+            check: CheckStatus::new(cookie_jar, endpoint),
+        }
+    }
+}
+
+impl Flow for TokenLogin<'_> {
+    fn resume(&mut self, input: Input) -> Result<Step> {
+        if let Input::Start = input {
+            self.token.store(self.check.cookie_jar, self.check.endpoint);
+        }
+
+        Ok(match self.check.resume(input)? {
+            Step::Done(status) => Step::Done(status.with_reject_reason(RejectReason::TokenExpired)),
+            step => step,
+        })
+    }
+}
+
+/// Logs in with the authorization from Wechat, finishing with
+/// [`RejectReason::PendingWechatAuthorization`] until Wechat authorizes.
+#[cfg(feature = "wechat")]
+pub(crate) struct WechatLogin<'a> {
+    wechat: &'a Wechat,
+    check: CheckStatus<'a>,
+    authorized: bool,
+}
+
+#[cfg(feature = "wechat")]
+impl<'a> WechatLogin<'a> {
+    pub(crate) fn new(
+        wechat: &'a Wechat,
+        cookie_jar: &'a CookieJar,
+        endpoint: &'a Endpoint,
+    ) -> Self {
+        WechatLogin {
+            wechat,
+            check: CheckStatus::new(cookie_jar, endpoint),
+            authorized: false,
+        }
+    }
+}
+
+#[cfg(feature = "wechat")]
+impl Flow for WechatLogin<'_> {
+    fn resume(&mut self, input: Input) -> Result<Step> {
+        let endpoint = self.check.endpoint;
+
+        match (self.authorized, input) {
+            (false, Input::Start) => Ok(Step::send_idempotent(HttpRequest::get(parse_url(
+                &self.wechat.get_verify_url(endpoint.wechat_verify_url()),
+            )?))),
+            (false, Input::Response(response)) => match is_authorized(&response.body) {
+                false => Ok(Step::Done(UserStatus::Rejected {
+                    reason: Some(RejectReason::PendingWechatAuthorization),
+                })),
+                true => {
+                    self.authorized = true;
+                    check_status(endpoint)
+                }
+            },
+            (true, input @ Input::Response(_)) => self.check.resume(input),
+            _ => unexpected("wechat login"),
+        }
+    }
+}
+
+/// Resolve the status from the login page of `endpoint` and the cookies in `cookie_jar`.
+pub(crate) fn status_from_page(
+    cookie_jar: &CookieJar,
+    endpoint: &Endpoint,
+    html: &str,
+) -> UserStatus {
+    let token = cookie_jar
+        .cookies(endpoint.cookie_url())
+        .and_then(|h| h.to_str().map(|s| s.to_owned()).ok())
+        .and_then(|s| find_cookie_value(&s, endpoint.cookie_name()));
+
+    UserStatus::from_response_html(html, token)
+}
+
+/// Find the reason of a failed login in the page responded to the login form.
+pub(crate) fn rejected_from_page(html: &str) -> Option<UserStatus> {
+    match UserStatus::from_response_html(html, None) {
+        status @ UserStatus::Rejected { reason: Some(_) } => Some(status),
+        _ => None,
+    }
+}
+
+/// Confirm that a request to `url` succeeded with `status`.
+pub(crate) fn ensure_success(url: &Url, status: StatusCode) -> Result<()> {
+    match status.is_success() {
+        true => Ok(()),
+        false => Err(Error::HttpStatus {
+            url: url.to_string(),
+            status,
+        }),
+    }
+}
+
+/// Confirm the status checked after logout.
+pub(crate) fn ensure_logged_out(status: UserStatus) -> Result<()> {
+    match status.is_rejected() {
+        true => Ok(()),
+        false => Err(Error::StatusConflict),
+    }
+}
+
+fn find_cookie_value(raw: &str, cookie_name: &str) -> Option<String> {
+    match raw.find(cookie_name) {
+        None => None,
+        Some(i) => {
+            let start_index = i + cookie_name.len() + 1;
+            let sub = &raw[start_index..];
+            Some(match sub.find(';') {
+                None => sub.to_owned(),
+                Some(end_index) => sub[..end_index].to_owned(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::flow::find_cookie_value;
+
+    #[test]
+    fn test_find_cookie_value() {
+        let table = vec![
+            ("refresh=1; wengine_vpn_ticketwebvpn_neu_edu_cn=3c2cca8a854e8122", "wengine_vpn_ticketwebvpn_neu_edu_cn", Some("3c2cca8a854e8122".to_owned())),
+            ("CASTGC=TGT-20180000-1827000-izbHeCI9y53RyIpMoYKxKbdyjtkgmfOy0NwbJHHiwXQabRYYKK-tpass; Language=zh_CN; jsessionid_tpass=ZLr9vBLe0xcX0nPsDfv3WASFiziyH-sMuy4CDoiIcqJkASjw136y!-1701433832", "CASTGC", Some("TGT-20180000-1827000-izbHeCI9y53RyIpMoYKxKbdyjtkgmfOy0NwbJHHiwXQabRYYKK-tpass".to_owned())),
+            ("CASTGC=TGT-20180000-1827000-izbHeCI9y53RyIpMoYKxKbdyjtkgmfOy0NwbJHHiwXQabRYYKK-tpass; Language=zh_CN; jsessionid_tpass=ZLr9vBLe0xcX0nPsDfv3WASFiziyH-sMuy4CDoiIcqJkASjw136y!-1701433832", "jsessionid_tpass", Some("ZLr9vBLe0xcX0nPsDfv3WASFiziyH-sMuy4CDoiIcqJkASjw136y!-1701433832".to_owned())),
+            ("", "wengine_vpn_ticketwebvpn_neu_edu_cn", None),
+        ];
+
+        for (raw, name, expected) in table {
+            assert_eq!(find_cookie_value(raw, name), expected)
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::RwLock;
+
+use cookie_store::{Cookie, CookieStore as Store, RawCookie};
+use reqwest::{cookie::CookieStore, header::HeaderValue, Url};
+
+/// A cookie store used by [`Session`](crate::session::Session).
+///
+/// Unlike [`Jar`](crate::reqwest::cookie::Jar), cookies inside can be enumerated,
+/// so that the state of a [`Session`](crate::session::Session) can be saved and restored.
+#[derive(Debug, Default)]
+pub struct CookieJar(RwLock<Store>);
+
+impl CookieJar {
+    /// Add a cookie to this jar.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use neust::{CookieJar, reqwest::Url};
+    /// let cookie_jar = CookieJar::default();
+    /// let url = Url::parse("https://example.com").unwrap();
+    /// cookie_jar.add_cookie_str("some_new_cookie=123", &url);
+    /// ```
+    pub fn add_cookie_str(&self, cookie: &str, url: &Url) {
+        let cookies = RawCookie::parse(cookie)
+            .ok()
+            .map(|c| c.into_owned())
+            .into_iter();
+        self.0.write().unwrap().store_response_cookies(cookies, url);
+    }
+
+    pub(crate) fn unexpired_cookies(&self) -> Vec<Cookie<'static>> {
+        self.0.read().unwrap().iter_unexpired().cloned().collect()
+    }
+
+    /// Remove cookies named one of `names` for the domain of `url`, whatever their paths are,
+    /// so that cookies set for deeper paths than `url` are removed as well.
+    pub(crate) fn remove_cookies(&self, url: &Url, names: &[impl AsRef<str>]) {
+        let mut store = self.0.write().unwrap();
+
+        let keys = store
+            .iter_any()
+            .filter(|c| c.domain.matches(url))
+            .filter(|c| names.iter().any(|n| n.as_ref() == c.name()))
+            .map(|c| {
+                (
+                    String::from(&c.domain),
+                    String::from(&c.path),
+                    c.name().to_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (domain, path, name) in keys {
+            store.remove(&domain, &path, &name);
+        }
+    }
+
+    pub(crate) fn replace_cookies(&self, cookies: &[Cookie<'static>]) {
+        let store = Store::from_cookies(
+            cookies
+                .iter()
+                .cloned()
+                .map(Ok::<_, std::convert::Infallible>),
+            false,
+        )
+        .unwrap();
+        *self.0.write().unwrap() = store;
+    }
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers.filter_map(|v| {
+            std::str::from_utf8(v.as_bytes())
+                .ok()
+                .and_then(|s| RawCookie::parse(s).ok())
+                .map(|c| c.into_owned())
+        });
+        self.0.write().unwrap().store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let raw = self
+            .0
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        match raw.is_empty() {
+            true => None,
+            false => HeaderValue::from_str(&raw).ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{cookie::CookieStore, Url};
+
+    use crate::jar::CookieJar;
+
+    #[test]
+    fn test_cookie_jar_remove() {
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/").unwrap();
+
+        let jar = CookieJar::default();
+        jar.add_cookie_str("CASTGC=TGT-1; Path=/tpass/", &url);
+        jar.add_cookie_str("jsessionid_tpass=abc; Path=/tpass", &url);
+        jar.add_cookie_str("Language=zh_CN", &url);
+        let login_url = Url::parse("https://pass.neu.edu.cn/tpass/login/").unwrap();
+        jar.add_cookie_str("CASTGC=TGT-2; Path=/tpass/login/", &login_url);
+        let portal_url = Url::parse("https://portal.neu.edu.cn/tpass/").unwrap();
+        jar.add_cookie_str("CASTGC=TGT-3; Path=/tpass/", &portal_url);
+        jar.remove_cookies(&url, &["CASTGC", "jsessionid_tpass"]);
+
+        let header = jar.cookies(&url).unwrap();
+        assert_eq!(header.to_str().unwrap(), "Language=zh_CN");
+        let header = jar.cookies(&login_url).unwrap();
+        assert_eq!(header.to_str().unwrap(), "Language=zh_CN");
+        let header = jar.cookies(&portal_url).unwrap();
+        assert_eq!(header.to_str().unwrap(), "CASTGC=TGT-3");
+    }
+
+    #[test]
+    fn test_cookie_jar_replace() {
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/").unwrap();
+
+        let jar_a = CookieJar::default();
+        jar_a.add_cookie_str("CASTGC=TGT-1; Path=/tpass/", &url);
+        jar_a.add_cookie_str("Language=zh_CN", &url);
+
+        let jar_b = CookieJar::default();
+        jar_b.add_cookie_str("other=1", &url);
+        jar_b.replace_cookies(&jar_a.unexpired_cookies());
+
+        let header = jar_b.cookies(&url).unwrap();
+        let header = header.to_str().unwrap();
+        assert!(header.contains("CASTGC=TGT-1"));
+        assert!(header.contains("Language=zh_CN"));
+        assert!(!header.contains("other=1"));
+    }
+}
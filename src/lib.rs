@@ -30,7 +30,7 @@
 //! ```no_run
 //! # #[cfg(feature = "webvpn")]
 //! # async fn doc() -> Result<(), neust::Error> {
-//! use neust::{Session, auth, webvpn};
+//! use neust::{Session, auth};
 //!
 //! let credential = auth::Credential::new("username", "password");
 //! let session = Session::new();
@@ -38,13 +38,11 @@
 //!     || !session.login_via_webvpn(&credential).await?.is_active() {
 //!     panic!("fail");
 //! }
-//! let client = session.client();
-//! let request = client
-//!     .get(webvpn::encrypt_url(
-//!         "http://219.216.96.4/eams/teach/grade/course/person!search.action?semesterId=0",
-//!     ))
-//!     .build()?;
-//! let response = client.execute(request).await?;
+//! let response = session
+//!     .webvpn_client()
+//!     .get("http://219.216.96.4/eams/teach/grade/course/person!search.action?semesterId=0")
+//!     .send()
+//!     .await?;
 //! # Ok(())
 //! # }
 //! ```
@@ -74,6 +72,14 @@
 //!
 //! - **webvpn**: supports for WebVPN endpoint.
 //! - **wechat**: supports for authorization by Wechat.
+//! - **des-encrypt**: supports for submitting encrypted password, see [`Credential::encrypted`](crate::auth::Credential::encrypted).
+//! - **serde**: provides serialization and deserialization for [`UserStatus`], [`SessionSnapshot`]
+//!   and auth methods, see also [`auth::with_password`], and saves sessions to JSON files
+//!   by [`Session::save_to`].
+//! - **test-server**: provides a local mock of the CAS in [`testing`].
+//! - **proxy**: builds the `neust-proxy` binary, a local HTTP proxy to intranet services
+//!   through the WebVPN.
+//! - **blocking**: provides a blocking [`Session`](crate::blocking::Session).
 //! - **native-tls** *(enabled by default)*: Enables TLS functionality provided by `native-tls`.
 //! - **rustls-tls**: Enables TLS functionality provided by `rustls`.
 //! - **json**: Provides serialization and deserialization for JSON bodies.
@@ -81,21 +87,52 @@
 #![deny(missing_debug_implementations, unreachable_pub, missing_docs)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub use async_trait::async_trait;
 pub use reqwest;
 
+pub use self::endpoint::*;
 pub use self::error::*;
+pub use self::jar::*;
+pub use self::limit::*;
+pub use self::manager::*;
+pub use self::page::*;
+pub use self::reset::*;
+pub use self::retry::*;
+pub use self::service::*;
 pub use self::session::*;
+pub use self::snapshot::*;
 pub use self::status::*;
+pub use self::transport::{HttpRequest, HttpResponse};
 
 mod error;
+mod jar;
+mod limit;
+mod manager;
+mod page;
+mod reset;
+mod retry;
+mod service;
 mod session;
+mod snapshot;
 mod status;
 
 mod endpoint;
+mod flow;
+mod transport;
 
 pub mod auth;
 
+pub mod cas;
+
 #[cfg(feature = "webvpn")]
 pub mod webvpn;
 
+#[cfg(feature = "test-server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-server")))]
+pub mod testing;
+
+#[cfg(feature = "blocking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+pub mod blocking;
+
 pub mod doc;
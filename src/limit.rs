@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Hosts limited by default, the CAS and the WebVPN.
+static DEFAULT_HOSTS: &[&str] = &["pass.neu.edu.cn", "webvpn.neu.edu.cn"];
+
+/// A token bucket rate limiter, keeping one bucket per host.
+///
+/// Each bucket holds up to `burst` tokens and gains one token every `interval`.
+/// Requests of auth flows to limited hosts take one token each, waiting for the next
+/// token when the bucket is empty, so that bursty logins from many sessions on one host
+/// do not get blocked by the CAS.
+///
+/// Pass it to [`Session::with_rate_limiter`](crate::session::Session::with_rate_limiter),
+/// one for each session, or one shared by many sessions through an [`Arc`].
+/// Blocking sessions take it the same way.
+///
+/// Requests sent by [`Session::client`](crate::session::Session::client) directly
+/// are not limited, use [`RateLimiter::acquire`] before sending them if needed.
+///
+/// # Examples
+///
+/// ```
+/// # use neust::{RateLimiter, Session};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// // at most 5 requests at once, then 2 requests per second.
+/// let limiter = Arc::new(RateLimiter::new(5, Duration::from_millis(500)));
+///
+/// let sessions: Vec<Session> = (0..100)
+///     .map(|_| Session::new().with_rate_limiter(limiter.clone()))
+///     .collect();
+/// ```
+///
+/// [`Arc`]: std::sync::Arc
+#[derive(Debug)]
+pub struct RateLimiter {
+    burst: u32,
+    interval: Duration,
+    hosts: Vec<String>,
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    buckets: HashMap<String, Bucket>,
+    metrics: RateLimiterMetrics,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens, negative when requests are waiting for tokens in the future.
+    tokens: f64,
+    updated: Instant,
+}
+
+/// How long requests waited in a [`RateLimiter`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct RateLimiterMetrics {
+    /// The number of requests to limited hosts.
+    pub requests: u64,
+    /// The number of requests that waited for tokens.
+    pub delayed: u64,
+    /// The total waiting time of all requests.
+    pub total_wait: Duration,
+    /// The longest waiting time of a single request.
+    pub max_wait: Duration,
+}
+
+impl RateLimiter {
+    /// Creates a [`RateLimiter`] allowing `burst` requests at once and one more request
+    /// every `interval` per host, limiting hosts of the CAS and the WebVPN.
+    ///
+    /// `burst` is at least 1.
+    pub fn new(burst: u32, interval: Duration) -> Self {
+        RateLimiter {
+            burst: burst.max(1),
+            interval,
+            hosts: DEFAULT_HOSTS.iter().map(|&h| h.to_owned()).collect(),
+            state: Mutex::new(LimiterState::default()),
+        }
+    }
+
+    /// Replace the limited hosts, e.g. for a custom [`Endpoint`](crate::endpoint::Endpoint).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use neust::RateLimiter;
+    /// # use std::time::Duration;
+    /// let limiter = RateLimiter::new(5, Duration::from_secs(1))
+    ///     .with_hosts(["pass.neu.edu.cn", "127.0.0.1"]);
+    /// ```
+    pub fn with_hosts<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.hosts = hosts.into_iter().map(|h| h.into()).collect();
+        self
+    }
+
+    /// Get the limited hosts.
+    pub fn hosts(&self) -> &[String] {
+        &self.hosts
+    }
+
+    /// Get the metrics of all requests so far.
+    pub fn metrics(&self) -> RateLimiterMetrics {
+        self.state().metrics.clone()
+    }
+
+    /// Wait until a request to `host` is allowed, returns how long it waited.
+    ///
+    /// Returns immediately if `host` is not limited.
+    pub async fn acquire(&self, host: &str) -> Duration {
+        let wait = self.reserve(host, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        wait
+    }
+
+    /// Block the current thread until a request to `host` is allowed,
+    /// returns how long it waited.
+    ///
+    /// The blocking counterpart of [`RateLimiter::acquire`].
+    #[cfg(feature = "blocking")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+    pub fn acquire_blocking(&self, host: &str) -> Duration {
+        let wait = self.reserve(host, Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+        wait
+    }
+
+    /// Take a token from the bucket of `host` at `now`, returns how long to wait for it.
+    fn reserve(&self, host: &str, now: Instant) -> Duration {
+        if !self.hosts.iter().any(|h| h == host) {
+            return Duration::ZERO;
+        }
+
+        let burst = f64::from(self.burst);
+        let interval = self.interval.as_secs_f64();
+
+        let mut state = self.state();
+        let bucket = state.buckets.entry(host.to_owned()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = match interval > 0.0 {
+            true => (bucket.tokens + elapsed / interval).min(burst),
+            false => burst,
+        };
+        bucket.updated = now;
+        bucket.tokens -= 1.0;
+
+        let wait = match bucket.tokens < 0.0 {
+            true => Duration::from_secs_f64(-bucket.tokens * interval),
+            false => Duration::ZERO,
+        };
+
+        let metrics = &mut state.metrics;
+        metrics.requests += 1;
+        if !wait.is_zero() {
+            metrics.delayed += 1;
+            metrics.total_wait += wait;
+            metrics.max_wait = metrics.max_wait.max(wait);
+        }
+
+        wait
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LimiterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use reqwest::Method;
+
+    use crate::limit::RateLimiter;
+    use crate::session::Session;
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(1));
+        let now = Instant::now();
+        let table = vec![
+            ("pass.neu.edu.cn", 0, 0),
+            ("pass.neu.edu.cn", 0, 0),
+            ("pass.neu.edu.cn", 0, 1000),
+            ("pass.neu.edu.cn", 0, 2000),
+            ("webvpn.neu.edu.cn", 0, 0),
+            ("example.com", 0, 0),
+            ("pass.neu.edu.cn", 2500, 500),
+            ("pass.neu.edu.cn", 5000, 0),
+        ];
+
+        for (host, at, expected) in table {
+            let wait = limiter.reserve(host, now + Duration::from_millis(at));
+            assert_eq!(wait.as_millis(), expected, "{} at {}", host, at);
+        }
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.requests, 7);
+        assert_eq!(metrics.delayed, 3);
+        assert_eq!(metrics.total_wait, Duration::from_millis(3500));
+        assert_eq!(metrics.max_wait, Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_session_rate_limit() {
+        let login_page = "<html><head><title>智慧东大--统一身份认证</title></head></html>";
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    MockResponse::ok(login_page),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    MockResponse::ok(login_page),
+                ),
+        );
+        let limiter = Arc::new(RateLimiter::new(1, Duration::from_millis(50)));
+        let session = Session::with_transport(transport.clone()).with_rate_limiter(limiter.clone());
+
+        session.check_status().await.unwrap();
+        session.check_status().await.unwrap();
+        transport.assert_done();
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.requests, 2);
+        assert_eq!(metrics.delayed, 1);
+        assert!(metrics.max_wait > Duration::from_millis(40));
+    }
+}
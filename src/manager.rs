@@ -0,0 +1,272 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+use crate::error::{Error, Result};
+use crate::session::{AuthMethod, Session};
+use crate::status::UserStatus;
+
+/// A [`Session`] that logs in again automatically when the user status becomes
+/// [`Rejected`](UserStatus::Rejected), e.g. after the token expires.
+///
+/// Concurrent callers of [`SessionManager::ensure_active`] wait for a single in-flight
+/// check or re-login, instead of contacting the CAS at the same time.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn doc() -> Result<(), neust::Error> {
+/// use neust::{auth, Session, SessionManager};
+/// use std::time::Duration;
+///
+/// let credential = auth::Credential::new("username", "password");
+/// let manager = SessionManager::new(Session::new(), credential)
+///     .check_interval(Duration::from_secs(60));
+///
+/// let session = manager.ensure_active().await?;
+/// let response = session.client().get("https://portal.neu.edu.cn/").send().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SessionManager<A> {
+    session: Session,
+    auth: A,
+    #[cfg(feature = "webvpn")]
+    via_webvpn: bool,
+    check_interval: Duration,
+    last_active: Mutex<Option<Instant>>,
+}
+
+impl<A: AuthMethod> SessionManager<A> {
+    /// Creates a [`SessionManager`] that logs into `session` with `auth` when needed.
+    pub fn new(session: Session, auth: A) -> Self {
+        SessionManager {
+            session,
+            auth,
+            #[cfg(feature = "webvpn")]
+            via_webvpn: false,
+            check_interval: Duration::ZERO,
+            last_active: Mutex::new(None),
+        }
+    }
+
+    /// Also keep the user logged in via
+    /// [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint). Disabled by default.
+    #[cfg(feature = "webvpn")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "webvpn")))]
+    pub fn via_webvpn(mut self, enabled: bool) -> Self {
+        self.via_webvpn = enabled;
+        self
+    }
+
+    /// Skip checking the user status if it has been confirmed active within `interval`.
+    ///
+    /// Defaults to zero, which means the status is checked on every
+    /// [`SessionManager::ensure_active`].
+    pub fn check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+
+    /// Get the managed [`Session`] without checking the user status.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Make sure the user is active, logging in again if the status is
+    /// [`Rejected`](UserStatus::Rejected), and then returns the managed [`Session`].
+    ///
+    /// Returns [`Error::ReauthFailed`](crate::error::Error::ReauthFailed) if the user
+    /// is still not active after logging in again, or
+    /// [`Error::UserInactive`](crate::error::Error::UserInactive) without logging in again
+    /// if the user is online but not active, e.g. the password needs reset.
+    pub async fn ensure_active(&self) -> Result<&Session> {
+        let mut last_active = self.last_active.lock().await;
+
+        if matches!(*last_active, Some(t) if t.elapsed() < self.check_interval) {
+            return Ok(&self.session);
+        }
+
+        *last_active = None;
+
+        let status = self.session.check_status().await?;
+        self.reauth(status, Session::relogin).await?;
+
+        #[cfg(feature = "webvpn")]
+        if self.via_webvpn {
+            let status = self.session.check_status_via_webvpn().await?;
+            self.reauth(status, Session::relogin_via_webvpn).await?;
+        }
+
+        *last_active = Some(Instant::now());
+
+        Ok(&self.session)
+    }
+
+    /// Forget the last confirmed status, so that the next
+    /// [`SessionManager::ensure_active`] checks the user status on the CAS.
+    pub async fn invalidate(&self) {
+        *self.last_active.lock().await = None;
+    }
+
+    /// Log in again by `relogin` iff `status` is rejected, and confirm the user is active.
+    async fn reauth<'a, F, Fut>(&'a self, status: UserStatus, relogin: F) -> Result<()>
+    where
+        F: FnOnce(&'a Session, &'a A) -> Fut,
+        Fut: std::future::Future<Output = Result<UserStatus>>,
+    {
+        match status {
+            UserStatus::Active { .. } => Ok(()),
+            status if status.is_rejected() => match relogin(&self.session, &self.auth).await? {
+                UserStatus::Active { .. } => Ok(()),
+                status => Err(Error::ReauthFailed { status }),
+            },
+            status => Err(Error::UserInactive { status }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use reqwest::{Method, StatusCode};
+
+    use crate::auth::Token;
+    use crate::error::Error;
+    use crate::manager::SessionManager;
+    use crate::retry::RetryPolicy;
+    use crate::session::Session;
+    use crate::status::{RejectReason, UserStatus};
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    static LOGIN_URL: &str = "https://pass.neu.edu.cn/tpass/login";
+    static LOGIN_PAGE: &str = "<html><head><title>智慧东大--统一身份认证</title></head></html>";
+    static PORTAL: &str = r#"<html><head><title>个人中心</title></head>
+<script>var id_number = "20180000";</script></html>"#;
+
+    fn token_manager(transport: &Arc<MockTransport>) -> SessionManager<Token> {
+        let session = Session::with_transport(transport.clone());
+        SessionManager::new(session, Token::new("TGT-20180000-1-tpass"))
+    }
+
+    #[tokio::test]
+    async fn test_relogin_when_rejected() {
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(LOGIN_PAGE))
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(PORTAL)),
+        );
+        let manager = token_manager(&transport);
+
+        manager.ensure_active().await.unwrap();
+        transport.assert_done();
+        assert_eq!(transport.cookies()[0], None);
+        assert_eq!(
+            transport.cookies()[1].as_deref(),
+            Some("CASTGC=TGT-20180000-1-tpass")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_relogin_failed() {
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(LOGIN_PAGE))
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(LOGIN_PAGE))
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(PORTAL)),
+        );
+        let manager = token_manager(&transport).check_interval(Duration::from_secs(60));
+
+        match manager.ensure_active().await {
+            Err(Error::ReauthFailed { status }) => assert_eq!(
+                status.get_reject_reason(),
+                Some(&RejectReason::TokenExpired)
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // failures are not cached.
+        manager.ensure_active().await.unwrap();
+        transport.assert_done();
+    }
+
+    #[tokio::test]
+    async fn test_inactive_without_relogin() {
+        let need_reset = "<html><head><title>智慧东大</title></head></html>";
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            LOGIN_URL,
+            MockResponse::ok(need_reset),
+        ));
+        let manager = token_manager(&transport);
+
+        assert!(matches!(
+            manager.ensure_active().await,
+            Err(Error::UserInactive {
+                status: UserStatus::NeedReset { .. }
+            })
+        ));
+        transport.assert_done();
+    }
+
+    #[tokio::test]
+    async fn test_check_interval_and_invalidate() {
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(PORTAL))
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(PORTAL)),
+        );
+        let manager = token_manager(&transport).check_interval(Duration::from_secs(60));
+
+        manager.ensure_active().await.unwrap();
+        manager.ensure_active().await.unwrap();
+        assert_eq!(transport.requests().len(), 1);
+
+        manager.invalidate().await;
+        manager.ensure_active().await.unwrap();
+        manager.ensure_active().await.unwrap();
+        transport.assert_done();
+
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(PORTAL))
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(PORTAL)),
+        );
+        let manager = token_manager(&transport);
+
+        // without an interval, every call checks the status.
+        manager.ensure_active().await.unwrap();
+        manager.ensure_active().await.unwrap();
+        transport.assert_done();
+    }
+
+    #[tokio::test]
+    async fn test_single_flight() {
+        // the first check sleeps before retrying, so that the second call runs meanwhile.
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    LOGIN_URL,
+                    MockResponse::ok("").status(StatusCode::SERVICE_UNAVAILABLE),
+                )
+                .expect(Method::GET, LOGIN_URL, MockResponse::ok(PORTAL)),
+        );
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(50),
+            jitter: false,
+            ..RetryPolicy::default()
+        };
+        let session = Session::with_transport(transport.clone()).with_retry_policy(policy);
+        let manager = SessionManager::new(session, Token::new("TGT-20180000-1-tpass"))
+            .check_interval(Duration::from_secs(60));
+
+        let (a, b) = tokio::join!(manager.ensure_active(), manager.ensure_active());
+        a.unwrap();
+        b.unwrap();
+        transport.assert_done();
+    }
+}
@@ -0,0 +1,308 @@
+//! Parsers for pages of the CAS, shared by auth flows and [`diagnose`].
+//!
+//! They are tested against anonymized captures in `tests/fixtures`, which should be
+//! refreshed whenever the CAS changes its pages.
+
+use std::fmt::{Display, Formatter};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+
+use crate::auth::captcha::CaptchaField;
+use crate::auth::form::Form;
+use crate::endpoint::Endpoint;
+use crate::status::RejectReason;
+
+static LOGIN_TITLE: &str = "智慧东大--统一身份认证";
+static NEED_RESET_TITLE: &str = "智慧东大";
+static BANNED_TITLE: &str = "系统提示";
+
+/// Hidden inputs the login form must provide.
+pub(crate) static LOGIN_FORM_FIELDS: &[&str] = &["lt", "execution", "_eventId"];
+
+/// The kind of a page responded by the CAS or the WebVPN.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PageKind {
+    /// The login page of the CAS, possibly with an error message.
+    Login,
+    /// The portal, the default service the CAS redirects logged-in users to.
+    Portal,
+    /// The page asking the logged-in user to reset the password.
+    NeedReset,
+    /// The page telling the logged-in user that the account is banned.
+    Banned,
+    /// The login page of the WebVPN itself, shown before the WebVPN session is set up.
+    WebVpnLogin,
+    /// None of the above, usually meaning that the CAS has changed.
+    Unknown,
+}
+
+impl Display for PageKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PageKind::Login => write!(f, "login"),
+            PageKind::Portal => write!(f, "portal"),
+            PageKind::NeedReset => write!(f, "need reset"),
+            PageKind::Banned => write!(f, "banned"),
+            PageKind::WebVpnLogin => write!(f, "webvpn login"),
+            PageKind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Get the content of `<title>`.
+pub(crate) fn title(html: &str) -> Option<&str> {
+    lazy_static! {
+        static ref TITLE_RE: Regex = Regex::new(r"(?is)<title>\s*(.+?)\s*</title>").unwrap();
+    }
+
+    TITLE_RE
+        .captures(html)
+        .and_then(|cap| cap.get(1).map(|s| s.as_str()))
+}
+
+/// Get the username exposed by the portal.
+pub(crate) fn username(html: &str) -> Option<&str> {
+    lazy_static! {
+        static ref USERNAME_RE: Regex = Regex::new(r#"var id_number = "(.+?)""#).unwrap();
+    }
+
+    USERNAME_RE
+        .captures(html)
+        .and_then(|cap| cap.get(1).map(|s| s.as_str()))
+}
+
+/// Get the non-empty error message shown in the login page.
+pub(crate) fn error_message(html: &str) -> Option<&str> {
+    lazy_static! {
+        static ref ERROR_RE: Regex =
+            Regex::new(r#"(?s)id="errormsg"[^>]*>\s*([^<]*?)\s*<"#).unwrap();
+    }
+
+    ERROR_RE
+        .captures(html)
+        .and_then(|cap| cap.get(1).map(|s| s.as_str()))
+        .filter(|message| !message.is_empty())
+}
+
+/// Classify a page by its title and content.
+pub(crate) fn kind(html: &str) -> PageKind {
+    lazy_static! {
+        static ref WEBVPN_LOGIN_RE: Regex =
+            Regex::new(r#"(?i)<form[^>]*\saction="[^"]*/do-login""#).unwrap();
+    }
+
+    match title(html) {
+        Some(title) if title == LOGIN_TITLE => PageKind::Login,
+        Some(title) if title == NEED_RESET_TITLE => PageKind::NeedReset,
+        Some(title) if title == BANNED_TITLE => PageKind::Banned,
+        Some(title) if title.contains("WebVPN") => PageKind::WebVpnLogin,
+        _ if WEBVPN_LOGIN_RE.is_match(html) => PageKind::WebVpnLogin,
+        _ if username(html).is_some() => PageKind::Portal,
+        _ => PageKind::Unknown,
+    }
+}
+
+/// What [`diagnose`] found in a page.
+///
+/// Its [`Display`] output is a short plain-text report, suitable for bug reports.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Diagnosis {
+    /// The kind of the page.
+    pub kind: PageKind,
+    /// The content of `<title>`.
+    pub title: Option<String>,
+    /// The username exposed by the portal.
+    pub username: Option<String>,
+    /// The error message shown in the login page.
+    pub error_message: Option<String>,
+    /// The reason recognized from [`error_message`](Diagnosis::error_message).
+    pub reject_reason: Option<RejectReason>,
+    /// The absolute url the main form submits to,
+    /// resolved against the login url of [`Endpoint::direct`].
+    pub form_action: Option<String>,
+    /// Hidden inputs missing from the login form, only checked for login pages.
+    pub missing_fields: Vec<String>,
+    /// Whether the page asks for a captcha.
+    pub captcha: bool,
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_none = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_owned());
+
+        writeln!(f, "kind: {}", self.kind)?;
+        writeln!(f, "title: {}", or_none(&self.title))?;
+        writeln!(f, "username: {}", or_none(&self.username))?;
+        writeln!(f, "error message: {}", or_none(&self.error_message))?;
+        writeln!(
+            f,
+            "reject reason: {}",
+            or_none(&self.reject_reason.as_ref().map(|r| r.to_string()))
+        )?;
+        writeln!(f, "form action: {}", or_none(&self.form_action))?;
+        writeln!(f, "missing fields: {:?}", self.missing_fields)?;
+        write!(f, "captcha: {}", self.captcha)
+    }
+}
+
+/// Classify a captured page of the CAS and report what the parsers of this crate find in it.
+///
+/// It never fails, so that the report can be attached to bug reports when the CAS changes.
+///
+/// # Examples
+///
+/// ```
+/// use neust::{diagnose, PageKind};
+///
+/// let html = r#"<html><head><title>系统提示</title></head><body></body></html>"#;
+/// let diagnosis = diagnose(html);
+/// assert_eq!(diagnosis.kind, PageKind::Banned);
+/// println!("{}", diagnosis);
+/// ```
+pub fn diagnose(html: &str) -> Diagnosis {
+    let kind = kind(html);
+    let page_url = Url::parse(Endpoint::direct().login_url()).expect("login url is valid");
+    let form = Form::find(&page_url, html, "lt");
+    let error_message = error_message(html).filter(|_| kind == PageKind::Login);
+
+    Diagnosis {
+        kind,
+        title: title(html).map(|s| s.to_owned()),
+        username: username(html).map(|s| s.to_owned()),
+        error_message: error_message.map(|s| s.to_owned()),
+        reject_reason: error_message.map(RejectReason::from_message),
+        form_action: form.as_ref().map(|form| form.action.to_string()),
+        missing_fields: match (&form, kind) {
+            (Some(form), PageKind::Login) => form.missing_fields(LOGIN_FORM_FIELDS),
+            (None, PageKind::Login) => LOGIN_FORM_FIELDS.iter().map(|&f| f.to_owned()).collect(),
+            _ => Vec::new(),
+        },
+        captcha: CaptchaField::find(&page_url, html).is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::page::{self, diagnose, PageKind};
+    use crate::status::RejectReason;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            ($name, include_str!(concat!("../tests/fixtures/", $name)))
+        };
+    }
+
+    #[test]
+    fn test_kind() {
+        let table = vec![
+            (fixture!("login.html"), PageKind::Login),
+            (fixture!("login_captcha.html"), PageKind::Login),
+            (fixture!("login_error_password.html"), PageKind::Login),
+            (fixture!("login_error_locked.html"), PageKind::Login),
+            (fixture!("login_error_attempts.html"), PageKind::Login),
+            (fixture!("portal.html"), PageKind::Portal),
+            (fixture!("need_reset.html"), PageKind::NeedReset),
+            (fixture!("banned.html"), PageKind::Banned),
+            (fixture!("webvpn_login.html"), PageKind::WebVpnLogin),
+            (("empty", "<html></html>"), PageKind::Unknown),
+        ];
+
+        for ((name, html), expected) in table {
+            assert_eq!(page::kind(html), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_error_message() {
+        let table = vec![
+            (fixture!("login.html"), None),
+            (fixture!("login_captcha.html"), Some("请输入验证码")),
+            (
+                fixture!("login_error_password.html"),
+                Some("用户名或密码错误"),
+            ),
+            (
+                fixture!("login_error_locked.html"),
+                Some("账号已被锁定，请30分钟后再试"),
+            ),
+            (
+                fixture!("login_error_attempts.html"),
+                Some("登录失败次数过多，请稍后再试"),
+            ),
+            (fixture!("need_reset.html"), None),
+        ];
+
+        for ((name, html), expected) in table {
+            assert_eq!(page::error_message(html), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let (_, html) = fixture!("login.html");
+        let diagnosis = diagnose(html);
+        assert_eq!(diagnosis.kind, PageKind::Login);
+        assert_eq!(diagnosis.title.as_deref(), Some("智慧东大--统一身份认证"));
+        assert_eq!(
+            diagnosis.form_action.as_deref(),
+            Some("https://pass.neu.edu.cn/tpass/login;jsessionid=Xk3cM8xw9vQ2pL7nR1tY5bH0aE4sD6fG!-1701433832?service=https%3A%2F%2Fportal.neu.edu.cn%2Ftp_up%2F")
+        );
+        assert!(diagnosis.missing_fields.is_empty());
+        assert!(!diagnosis.captcha);
+        assert_eq!(diagnosis.reject_reason, None);
+
+        let table = vec![
+            (
+                fixture!("login_captcha.html"),
+                Some(RejectReason::CaptchaRequired { challenge: None }),
+                true,
+            ),
+            (
+                fixture!("login_error_password.html"),
+                Some(RejectReason::WrongCredentials),
+                false,
+            ),
+            (
+                fixture!("login_error_locked.html"),
+                Some(RejectReason::AccountLocked),
+                false,
+            ),
+            (
+                fixture!("login_error_attempts.html"),
+                Some(RejectReason::TooManyAttempts),
+                false,
+            ),
+        ];
+        for ((name, html), reason, captcha) in table {
+            let diagnosis = diagnose(html);
+            assert_eq!(diagnosis.reject_reason, reason, "{}", name);
+            assert_eq!(diagnosis.captcha, captcha, "{}", name);
+        }
+
+        let (_, html) = fixture!("portal.html");
+        let diagnosis = diagnose(html);
+        assert_eq!(diagnosis.username.as_deref(), Some("20180000"));
+        assert_eq!(diagnosis.form_action, None);
+
+        let (_, html) = fixture!("need_reset.html");
+        let diagnosis = diagnose(html);
+        assert_eq!(
+            diagnosis.form_action.as_deref(),
+            Some("https://pass.neu.edu.cn/tpass/resetPassword")
+        );
+        assert!(diagnosis.missing_fields.is_empty());
+
+        let html = r#"<html><head><title>智慧东大--统一身份认证</title></head><body>
+<form action="/tpass/login"><input type="hidden" name="lt" value="LT-1-tpass"/></form>
+</body></html>"#;
+        assert_eq!(diagnose(html).missing_fields, ["execution", "_eventId"]);
+
+        let report = diagnose(fixture!("banned.html").1).to_string();
+        assert!(report.starts_with("kind: banned\ntitle: 系统提示\n"));
+        assert!(report.ends_with("captcha: false"));
+    }
+}
@@ -0,0 +1,352 @@
+use std::fmt::{Display, Formatter};
+
+use crate::auth::form::Form;
+use crate::endpoint::{parse_url, Endpoint, ENDPOINT_DIRECT};
+use crate::error::{Error, Result};
+use crate::session::Session;
+use crate::status::UserStatus;
+use crate::transport::HttpRequest;
+
+/// The password policy checked locally before resetting the password.
+///
+/// See also [`Session::reset_password`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PasswordPolicy {
+    /// The minimum number of characters.
+    pub min_length: usize,
+    /// The maximum number of characters.
+    pub max_length: usize,
+    /// The minimum number of character classes among uppercase letters,
+    /// lowercase letters, digits and other characters.
+    pub min_char_classes: usize,
+}
+
+impl Default for PasswordPolicy {
+    /// A conservative default, since the CAS does not publish its policy:
+    /// 8 to 20 characters in at least 3 character classes.
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 8,
+            max_length: 20,
+            min_char_classes: 3,
+        }
+    }
+}
+
+/// The reason why a new password violates the [`PasswordPolicy`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PolicyViolation {
+    /// The password is shorter than the minimum length.
+    TooShort {
+        /// The minimum length
+        min: usize,
+    },
+    /// The password is longer than the maximum length.
+    TooLong {
+        /// The maximum length
+        max: usize,
+    },
+    /// The password consists of too few character classes.
+    TooFewCharClasses {
+        /// The minimum number of character classes
+        min: usize,
+    },
+    /// The password contains whitespaces or control characters.
+    InvalidChar,
+    /// The new password is the same as the old one.
+    SameAsOld,
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyViolation::TooShort { min } => write!(f, "shorter than {} characters", min),
+            PolicyViolation::TooLong { max } => write!(f, "longer than {} characters", max),
+            PolicyViolation::TooFewCharClasses { min } => {
+                write!(f, "fewer than {} character classes", min)
+            }
+            PolicyViolation::InvalidChar => write!(f, "invalid characters"),
+            PolicyViolation::SameAsOld => write!(f, "same as the old password"),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Check whether the password can be changed from `old` to `new`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use neust::{PasswordPolicy, PolicyViolation};
+    /// let policy = PasswordPolicy::default();
+    /// assert_eq!(policy.check("old", "Neu@2023"), Ok(()));
+    /// assert_eq!(
+    ///     policy.check("old", "neu2023"),
+    ///     Err(PolicyViolation::TooShort { min: 8 })
+    /// );
+    /// ```
+    pub fn check(&self, old: &str, new: &str) -> std::result::Result<(), PolicyViolation> {
+        let length = new.chars().count();
+
+        if length < self.min_length {
+            return Err(PolicyViolation::TooShort {
+                min: self.min_length,
+            });
+        }
+        if length > self.max_length {
+            return Err(PolicyViolation::TooLong {
+                max: self.max_length,
+            });
+        }
+        if new.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(PolicyViolation::InvalidChar);
+        }
+
+        let classes = [
+            new.chars().any(|c| c.is_ascii_uppercase()),
+            new.chars().any(|c| c.is_ascii_lowercase()),
+            new.chars().any(|c| c.is_ascii_digit()),
+            new.chars().any(|c| !c.is_ascii_alphanumeric()),
+        ];
+        if classes.iter().filter(|&&c| c).count() < self.min_char_classes {
+            return Err(PolicyViolation::TooFewCharClasses {
+                min: self.min_char_classes,
+            });
+        }
+
+        if old == new {
+            return Err(PolicyViolation::SameAsOld);
+        }
+
+        Ok(())
+    }
+}
+
+impl Session {
+    /// Reset the password of the user in [`UserStatus::NeedReset`] logged in via
+    /// [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint), and returns the new status.
+    ///
+    /// `new` is checked against the default [`PasswordPolicy`] before submitting,
+    /// returns [`Error::PasswordPolicy`] if it is violated.
+    /// Returns [`Error::StatusConflict`] if the user does not need to reset the password,
+    /// or [`Error::ResetRejected`] with the error message if the CAS refuses to reset it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::{auth, Session, UserStatus};
+    /// let session = Session::new();
+    /// let status = session.login(&auth::Credential::new("username", "password")).await?;
+    /// if let UserStatus::NeedReset { .. } = status {
+    ///     let status = session.reset_password("password", "Neu@2023").await?;
+    ///     assert!(status.is_active());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn reset_password(&self, old: &str, new: &str) -> Result<UserStatus> {
+        self.reset_password_at(&ENDPOINT_DIRECT, &PasswordPolicy::default(), old, new)
+            .await
+    }
+
+    /// Reset the password via a custom [`Endpoint`], checking `new` against `policy`.
+    ///
+    /// Behaves the same as [`Session::reset_password`] except for the endpoint and the policy.
+    pub async fn reset_password_at(
+        &self,
+        endpoint: &Endpoint,
+        policy: &PasswordPolicy,
+        old: &str,
+        new: &str,
+    ) -> Result<UserStatus> {
+        policy
+            .check(old, new)
+            .map_err(|violation| Error::PasswordPolicy { violation })?;
+
+        let response = self
+            .execute_idempotent(HttpRequest::get(parse_url(endpoint.login_url())?))
+            .await?;
+        let html = response.text();
+        let page_url = response.url;
+
+        let status = crate::flow::status_from_page(self.cookie_jar(), endpoint, &html);
+        if !matches!(status, UserStatus::NeedReset { .. }) {
+            return Err(Error::StatusConflict);
+        }
+
+        let form = Form::find_all(&page_url, &html)
+            .into_iter()
+            .find(|form| !form.password_fields.is_empty())
+            .ok_or_else(|| Error::parse_page_error(page_url.as_str()))?;
+
+        let fields = build_reset_form(&form, old, new).ok_or_else(|| Error::MissingFormFields {
+            url: page_url.to_string(),
+            fields: vec!["password".to_owned()],
+        })?;
+
+        let response = self
+            .execute(HttpRequest::post_form(form.action, fields))
+            .await?;
+        crate::flow::ensure_success(&response.url, response.status)?;
+        if let Some(message) = crate::page::error_message(&response.text()) {
+            return Err(Error::ResetRejected {
+                message: message.to_owned(),
+            });
+        }
+
+        self.check_status_at(endpoint).await
+    }
+}
+
+/// Fill the password inputs of the reset form in document order, with the old password,
+/// the new password and its confirmation. The old password is omitted when the form
+/// has only two password inputs.
+fn build_reset_form(form: &Form, old: &str, new: &str) -> Option<Vec<(String, String)>> {
+    let values = match form.password_fields.len() {
+        3 => vec![old, new, new],
+        2 => vec![new, new],
+        _ => return None,
+    };
+
+    let mut fields = form.hidden_fields.clone();
+    fields.extend(
+        form.password_fields
+            .iter()
+            .zip(values)
+            .map(|(name, value)| (name.clone(), value.to_owned())),
+    );
+
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use reqwest::{Method, StatusCode, Url};
+
+    use crate::auth::form::Form;
+    use crate::endpoint::ENDPOINT_DIRECT;
+    use crate::error::Error;
+    use crate::reset::{build_reset_form, PasswordPolicy, PolicyViolation};
+    use crate::session::Session;
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    #[tokio::test]
+    async fn test_reset_password_failed() {
+        let reset_page = r#"<html><head><title>智慧东大</title></head><body>
+<form id="resetForm" action="/tpass/resetPassword" method="post">
+<input type="password" name="newPassword"/>
+<input type="password" name="confirmPassword"/>
+</form></body></html>"#;
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    MockResponse::ok(reset_page),
+                )
+                .expect(
+                    Method::POST,
+                    "https://pass.neu.edu.cn/tpass/resetPassword",
+                    MockResponse::ok("").status(StatusCode::INTERNAL_SERVER_ERROR),
+                ),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        let result = session
+            .reset_password_at(
+                &ENDPOINT_DIRECT,
+                &PasswordPolicy::default(),
+                "password",
+                "Neu@2023",
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::HttpStatus {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                ..
+            })
+        ));
+        transport.assert_done();
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let policy = PasswordPolicy::default();
+        let table = vec![
+            ("Neu@2023", Ok(())),
+            ("neu_2023", Ok(())),
+            ("NEU2023neu", Ok(())),
+            ("Ne@2023", Err(PolicyViolation::TooShort { min: 8 })),
+            (
+                "Neu@2023Neu@2023Neu@2023",
+                Err(PolicyViolation::TooLong { max: 20 }),
+            ),
+            ("Neu 2023 neu", Err(PolicyViolation::InvalidChar)),
+            (
+                "neu20232023",
+                Err(PolicyViolation::TooFewCharClasses { min: 3 }),
+            ),
+            ("Password1", Err(PolicyViolation::SameAsOld)),
+        ];
+
+        for (new, expected) in table {
+            assert_eq!(policy.check("Password1", new), expected)
+        }
+    }
+
+    #[test]
+    fn test_build_reset_form() {
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/login").unwrap();
+        let table = vec![
+            (
+                r#"<form id="resetForm" action="/tpass/resetPassword" method="post">
+<input type="hidden" name="token" value="t1"/>
+<input type="password" name="oldPassword"/>
+<input type="password" name="newPassword"/>
+<input type="password" name="confirmPassword"/>
+</form>"#,
+                vec![
+                    ("token", "t1"),
+                    ("oldPassword", "old"),
+                    ("newPassword", "new"),
+                    ("confirmPassword", "new"),
+                ],
+            ),
+            (
+                r#"<form id="resetForm" action="/tpass/resetPassword" method="post">
+<input type="hidden" name="token" value="t1"/>
+<input type="password" name="newPassword"/>
+<input type="password" name="confirmPassword"/>
+</form>"#,
+                vec![
+                    ("token", "t1"),
+                    ("newPassword", "new"),
+                    ("confirmPassword", "new"),
+                ],
+            ),
+        ];
+
+        for (html, expected) in table {
+            let form = Form::find_all(&url, html).pop().unwrap();
+            assert_eq!(
+                form.action.as_str(),
+                "https://pass.neu.edu.cn/tpass/resetPassword"
+            );
+            let fields = build_reset_form(&form, "old", "new").unwrap();
+            assert_eq!(fields.len(), expected.len());
+            for ((name, value), (expected_name, expected_value)) in fields.iter().zip(expected) {
+                assert_eq!(name, expected_name);
+                assert_eq!(value, expected_value);
+            }
+        }
+
+        let html = r#"<form><input type="password" name="newPassword"/></form>"#;
+        let form = Form::find_all(&url, html).pop().unwrap();
+        assert!(build_reset_form(&form, "old", "new").is_none());
+    }
+}
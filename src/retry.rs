@@ -0,0 +1,343 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::error::{Error, Result};
+use crate::session::Session;
+use crate::transport::{HttpRequest, HttpResponse};
+
+/// The policy to retry idempotent steps of auth flows on transient failures,
+/// e.g. the CAS being overloaded during course selection.
+///
+/// Only requests that can be repeated safely are retried: fetching the login page,
+/// checking the status, polling the Wechat authorization and logging out.
+/// The credential form is never submitted twice, since the CAS counts every
+/// submission as a login attempt.
+///
+/// A [`Session`] does not retry unless configured by [`Session::with_retry_policy`].
+///
+/// # Examples
+///
+/// ```
+/// # use neust::{RetryPolicy, Session};
+/// # use std::time::Duration;
+/// let policy = RetryPolicy {
+///     max_attempts: 5,
+///     max_backoff: Duration::from_secs(10),
+///     ..RetryPolicy::default()
+/// };
+/// let session = Session::new().with_retry_policy(policy);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: usize,
+    /// The delay before the first retry, doubled on every following retry.
+    pub initial_backoff: Duration,
+    /// The upper bound of delays.
+    pub max_backoff: Duration,
+    /// Whether to randomize delays within the upper half, so that sessions
+    /// failing at the same time do not retry at the same time.
+    pub jitter: bool,
+    /// Statuses responded by the CAS that are regarded as transient failures.
+    ///
+    /// Requests ending with these statuses are retried, and fail with
+    /// [`Error::HttpStatus`] once attempts run out. Other statuses are passed through.
+    /// Errors are retried iff [`Error::is_retryable`].
+    pub retryable_statuses: Vec<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts with delays from 200 milliseconds up to 5 seconds, with jitter,
+    /// retrying on `429`, `500`, `502`, `503` and `504`.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            jitter: true,
+            retryable_statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a [`RetryPolicy`] that never retries, used by [`Session`]s by default.
+    ///
+    /// No status is regarded as a failure, so responses are passed through as they are.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            retryable_statuses: Vec::new(),
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Judge the result of the `attempt`-th attempt, starting from 1, returns the delay
+    /// before the next attempt, or the final result.
+    pub(crate) fn judge(
+        &self,
+        attempt: usize,
+        result: Result<HttpResponse>,
+    ) -> ControlFlow<Result<HttpResponse>, Duration> {
+        let (result, retryable) = match result {
+            Ok(response) if self.retryable_statuses.contains(&response.status) => (
+                Err(Error::HttpStatus {
+                    url: response.url.to_string(),
+                    status: response.status,
+                }),
+                true,
+            ),
+            Ok(response) => (Ok(response), false),
+            Err(e) => {
+                let retryable = e.is_retryable();
+                (Err(e), retryable)
+            }
+        };
+
+        match retryable && attempt < self.max_attempts {
+            true => ControlFlow::Continue(self.backoff(attempt)),
+            false => ControlFlow::Break(result),
+        }
+    }
+
+    /// Get the delay before the `retry`-th retry, starting from 1.
+    fn backoff(&self, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(31) as u32;
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.max_backoff);
+
+        match self.jitter {
+            true => backoff / 2 + backoff.mul_f64(random_fraction() / 2.0),
+            false => backoff,
+        }
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// A random number in `[0, 1)`, seeded by the randomly keyed hasher of std.
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Error {
+    /// Returns `true` if the error is likely transient, so that the failed request
+    /// may succeed when retried, e.g. timeouts, refused connections and
+    /// [`Error::HttpStatus`] of `408`, `429` and `5xx`.
+    ///
+    /// [`RetryPolicy::retryable_statuses`] are retried regardless.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use neust::Error;
+    /// assert!(!Error::StatusConflict.is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::HttpStatus { status, .. } => is_retryable_status(*status),
+            Error::ReqwestError(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.is_request()
+                    || e.is_body()
+                    || matches!(e.status(), Some(status) if is_retryable_status(status))
+            }
+            Error::IoError(e) => matches!(
+                e.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::UnexpectedEof
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl Session {
+    /// Execute a request that can be repeated safely, retrying under the [`RetryPolicy`].
+    pub async fn execute_idempotent(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut attempt = 1;
+
+        loop {
+            let result = self.execute(request.clone()).await;
+
+            match self.retry_policy().judge(attempt, result) {
+                ControlFlow::Continue(backoff) => {
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                ControlFlow::Break(result) => return result,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Session {
+    /// Execute a request that can be repeated safely, retrying under the [`RetryPolicy`].
+    pub fn execute_idempotent(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut attempt = 1;
+
+        loop {
+            let result = self.execute(request.clone());
+
+            match self.retry_policy().judge(attempt, result) {
+                ControlFlow::Continue(backoff) => {
+                    std::thread::sleep(backoff);
+                    attempt += 1;
+                }
+                ControlFlow::Break(result) => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use reqwest::{Method, StatusCode};
+
+    use crate::error::Error;
+    use crate::retry::RetryPolicy;
+    use crate::session::Session;
+    use crate::transport::mock::{MockResponse, MockTransport};
+
+    fn policy(max_attempts: usize) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            jitter: false,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = policy(5);
+        let table = vec![(1, 1), (2, 2), (3, 4), (4, 4), (100, 4)];
+        for (retry, expected) in table {
+            assert_eq!(policy.backoff(retry), Duration::from_millis(expected));
+        }
+
+        let policy = RetryPolicy::default();
+        for retry in 1..10 {
+            let backoff = policy.backoff(retry);
+            assert!(backoff <= policy.max_backoff);
+            assert!(backoff >= policy.initial_backoff / 2);
+        }
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let table = vec![
+            (StatusCode::INTERNAL_SERVER_ERROR, true),
+            (StatusCode::SERVICE_UNAVAILABLE, true),
+            (StatusCode::REQUEST_TIMEOUT, true),
+            (StatusCode::TOO_MANY_REQUESTS, true),
+            (StatusCode::BAD_REQUEST, false),
+            (StatusCode::FORBIDDEN, false),
+            (StatusCode::NOT_FOUND, false),
+        ];
+        for (status, expected) in table {
+            let error = Error::HttpStatus {
+                url: "https://pass.neu.edu.cn/tpass/login".to_owned(),
+                status,
+            };
+            assert_eq!(error.is_retryable(), expected, "{}", status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_status_retry() {
+        let login_page = "<html><head><title>智慧东大--统一身份认证</title></head></html>";
+        let unavailable = || MockResponse::ok("").status(StatusCode::SERVICE_UNAVAILABLE);
+
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    unavailable(),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    unavailable(),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    MockResponse::ok(login_page),
+                ),
+        );
+        let session = Session::with_transport(transport.clone()).with_retry_policy(policy(3));
+        assert!(session.check_status().await.unwrap().is_rejected());
+        transport.assert_done();
+
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    unavailable(),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    unavailable(),
+                ),
+        );
+        let session = Session::with_transport(transport.clone()).with_retry_policy(policy(2));
+        match session.check_status().await {
+            Err(e @ Error::HttpStatus { .. }) => assert!(e.is_retryable()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        transport.assert_done();
+
+        // statuses out of `retryable_statuses` are passed through.
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            "https://pass.neu.edu.cn/tpass/login",
+            MockResponse::ok(login_page).status(StatusCode::FORBIDDEN),
+        ));
+        let session = Session::with_transport(transport.clone()).with_retry_policy(policy(3));
+        assert!(session.check_status().await.unwrap().is_rejected());
+        transport.assert_done();
+
+        // sessions do not retry by default, nor regard any status as a failure.
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            "https://pass.neu.edu.cn/tpass/login",
+            MockResponse::ok(login_page).status(StatusCode::SERVICE_UNAVAILABLE),
+        ));
+        let session = Session::with_transport(transport.clone());
+        assert!(session.check_status().await.unwrap().is_rejected());
+        transport.assert_done();
+    }
+}
@@ -0,0 +1,291 @@
+use reqwest::{Response, Url};
+
+use crate::endpoint::{parse_url, Endpoint, ENDPOINT_DIRECT};
+use crate::error::{Error, Result};
+use crate::session::Session;
+use crate::transport::HttpRequest;
+#[cfg(feature = "webvpn")]
+use crate::webvpn::WebVpnConfig;
+
+/// Max number of redirects followed when accessing a service, the same as reqwest.
+const MAX_REDIRECTS: usize = 10;
+
+/// The result of accessing a service connected to the CAS.
+///
+/// See also [`Session::access_service`].
+#[derive(Debug)]
+pub struct ServiceAccess {
+    hops: Vec<Url>,
+    response: Response,
+}
+
+impl ServiceAccess {
+    /// Get urls visited in the redirect chain, starting from the requested url and
+    /// ending with the url of the final response.
+    pub fn hops(&self) -> &[Url] {
+        &self.hops
+    }
+
+    /// Get the url of the final response.
+    pub fn url(&self) -> &Url {
+        self.hops.last().unwrap()
+    }
+
+    /// Get the final response.
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    /// Take the final response.
+    pub fn into_response(self) -> Response {
+        self.response
+    }
+}
+
+impl Session {
+    /// Access a service connected to the CAS as the user logged in via
+    /// [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint).
+    ///
+    /// Redirects between the service and the CAS are followed and recorded.
+    /// Returns [`Error::AccessRejected`] if the redirect chain ends at the login page of the CAS,
+    /// which means no user has logged in or the service refused the user.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::{auth, Session};
+    /// let session = Session::new();
+    /// session.login(&auth::Credential::new("username", "password")).await?;
+    /// let access = session.access_service("https://portal.neu.edu.cn/").await?;
+    /// println!("{:?}", access.hops());
+    /// let body = access.into_response().text().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn access_service(&self, url: &str) -> Result<ServiceAccess> {
+        self.access_service_at(&ENDPOINT_DIRECT, url).await
+    }
+
+    /// Access a service connected to the CAS via a custom [`Endpoint`].
+    ///
+    /// Behaves the same as [`Session::access_service`] except for the endpoint.
+    pub async fn access_service_at(&self, endpoint: &Endpoint, url: &str) -> Result<ServiceAccess> {
+        self.follow_redirects(url, &[endpoint.login_url()]).await
+    }
+
+    async fn follow_redirects(&self, url: &str, login_urls: &[&str]) -> Result<ServiceAccess> {
+        let mut url = parse_url(url)?;
+        let mut hops = Vec::new();
+
+        loop {
+            hops.push(url.clone());
+
+            let response = self
+                .execute(HttpRequest::get(url.clone()).no_redirect())
+                .await?;
+
+            if !response.status.is_redirection() {
+                if is_login_page(&url, login_urls) {
+                    return Err(Error::AccessRejected {
+                        url: hops[0].to_string(),
+                    });
+                }
+                return Ok(ServiceAccess {
+                    hops,
+                    response: response.into_reqwest(),
+                });
+            }
+
+            if hops.len() > MAX_REDIRECTS {
+                return Err(Error::TooManyRedirects {
+                    url: hops[0].to_string(),
+                });
+            }
+
+            url = response
+                .location()
+                .ok_or_else(|| Error::parse_page_error(url.as_str()))?;
+        }
+    }
+}
+
+#[cfg(feature = "webvpn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "webvpn")))]
+impl Session {
+    /// Access an intranet service connected to the CAS via
+    /// [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint).
+    ///
+    /// `url` is the original url of the service, which is encrypted by
+    /// [`webvpn::encrypt`](crate::webvpn::encrypt) automatically.
+    /// Returns [`Error::InvalidUrl`] without sending any request if `url` can not be
+    /// encrypted, or [`Error::AccessRejected`] if the redirect chain ends at the login page of
+    /// the CAS or the WebVPN.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::{auth, Session};
+    /// let session = Session::new();
+    /// let credential = auth::Credential::new("username", "password");
+    /// session.login(&credential).await?;
+    /// session.login_via_webvpn(&credential).await?;
+    /// let access = session
+    ///     .access_service_via_webvpn("http://219.216.96.4/eams/homeExt.action")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn access_service_via_webvpn(&self, url: &str) -> Result<ServiceAccess> {
+        self.access_service_via_webvpn_with(&crate::webvpn::WEBVPN_NEU, url)
+            .await
+    }
+
+    /// Access an intranet service connected to the CAS via the WebVPN of `config`.
+    ///
+    /// Same as [`Session::access_service_via_webvpn`], but for the session logged in via
+    /// [`WebVpnConfig::endpoint`].
+    pub async fn access_service_via_webvpn_with(
+        &self,
+        config: &WebVpnConfig,
+        url: &str,
+    ) -> Result<ServiceAccess> {
+        let encrypted = config.encrypt(&parse_url(url)?)?;
+
+        let endpoint = config.endpoint()?;
+        let webvpn_login_url = config.login_url();
+        let login_urls = [endpoint.login_url(), webvpn_login_url.as_str()];
+
+        self.follow_redirects(encrypted.as_str(), &login_urls).await
+    }
+}
+
+/// Returns `true` if `url` is one of `login_urls`, ignoring the query and the fragment.
+fn is_login_page(url: &Url, login_urls: &[&str]) -> bool {
+    login_urls
+        .iter()
+        .filter_map(|l| Url::parse(l).ok())
+        .any(|l| l.origin() == url.origin() && l.path() == url.path())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use reqwest::{Method, Url};
+
+    use crate::error::Error;
+    use crate::service::is_login_page;
+    use crate::session::Session;
+    use crate::transport::mock::{MockResponse, MockTransport};
+    #[cfg(feature = "webvpn")]
+    use crate::webvpn::WebVpnConfig;
+
+    #[tokio::test]
+    async fn test_access_service() {
+        let service = "https://portal.neu.edu.cn/tp_up/";
+        let login = "https://pass.neu.edu.cn/tpass/login?service=https%3A%2F%2Fportal.neu.edu.cn%2Ftp_up%2F";
+
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, service, MockResponse::redirect(login))
+                .expect(
+                    Method::GET,
+                    login,
+                    MockResponse::redirect("https://portal.neu.edu.cn/tp_up/?ticket=ST-1-tpass"),
+                )
+                .expect(Method::GET, service, MockResponse::redirect(service))
+                .expect(Method::GET, service, MockResponse::ok("portal")),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        let access = session.access_service(service).await.unwrap();
+        assert_eq!(access.hops().len(), 4);
+        assert_eq!(access.url().as_str(), service);
+        assert_eq!(access.response().url().as_str(), service);
+        assert_eq!(access.into_response().text().await.unwrap(), "portal");
+        transport.assert_done();
+
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(Method::GET, service, MockResponse::redirect(login))
+                .expect(Method::GET, login, MockResponse::ok("login page")),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        assert!(matches!(
+            session.access_service(service).await,
+            Err(Error::AccessRejected { .. })
+        ));
+        transport.assert_done();
+    }
+
+    #[cfg(feature = "webvpn")]
+    #[tokio::test]
+    async fn test_access_service_via_webvpn() {
+        let transport = Arc::new(MockTransport::new());
+        let session = Session::with_transport(transport.clone());
+
+        // urls that can not be encrypted are never requested directly.
+        for url in ["ftp://219.216.96.4/", "219.216.96.4/eams/"] {
+            assert!(matches!(
+                session.access_service_via_webvpn(url).await,
+                Err(Error::InvalidUrl { .. })
+            ));
+        }
+        assert!(transport.requests().is_empty());
+
+        let config = WebVpnConfig::new(Url::parse("http://127.0.0.1:8080/").unwrap());
+        let service = "http://219.216.96.4/eams/";
+        let encrypted = config.encrypt(&Url::parse(service).unwrap()).unwrap();
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    encrypted.as_str(),
+                    MockResponse::redirect("http://127.0.0.1:8080/login"),
+                )
+                .expect(
+                    Method::GET,
+                    "http://127.0.0.1:8080/login",
+                    MockResponse::ok("login page"),
+                ),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        assert!(matches!(
+            session
+                .access_service_via_webvpn_with(&config, service)
+                .await,
+            Err(Error::AccessRejected { .. })
+        ));
+        transport.assert_done();
+    }
+
+    #[test]
+    fn test_is_login_page() {
+        let login_urls = ["https://pass.neu.edu.cn/tpass/login"];
+        let table = vec![
+            (
+                "https://pass.neu.edu.cn/tpass/login?service=https%3A%2F%2Fportal.neu.edu.cn%2F",
+                true,
+            ),
+            ("https://pass.neu.edu.cn/tpass/login", true),
+            ("https://portal.neu.edu.cn/tp_up/view?m=up", false),
+            ("https://pass.neu.edu.cn/tpass/logout", false),
+            ("https://pass.neu.edu.cn/tpass/login-help", false),
+            ("https://pass.neu.edu.cn/tpass/login/", false),
+            ("http://pass.neu.edu.cn/tpass/login", false),
+            ("https://pass.neu.edu.cn:8443/tpass/login", false),
+            ("https://pass.neu.edu.cn/tpass/login#/", true),
+        ];
+
+        for (url, expected) in table {
+            assert_eq!(
+                is_login_page(&Url::parse(url).unwrap(), &login_urls),
+                expected
+            )
+        }
+    }
+}
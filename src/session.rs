@@ -1,22 +1,46 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use reqwest::{
-    cookie::{CookieStore, Jar},
-    Client, ClientBuilder,
-};
-use sealed::sealed;
+use reqwest::{Client, ClientBuilder};
 
 #[cfg(feature = "webvpn")]
 use crate::endpoint::ENDPOINT_WEBVPN;
 use crate::endpoint::{Endpoint, ENDPOINT_DIRECT};
 use crate::error::Result;
+use crate::flow::{self, CheckStatus, Flow, Input, Logout, Step};
+use crate::jar::CookieJar;
+use crate::limit::RateLimiter;
+use crate::retry::RetryPolicy;
 use crate::status::UserStatus;
+use crate::transport::{self, HttpRequest, HttpResponse, ReqwestTransport, Transport};
 
 /// An abstraction of auth method used in [`Session`].
 ///
-/// It's made sealed intentionally so custom implementations are disallowed.
-#[sealed(pub(crate))]
+/// Besides the implementations in [`auth`](crate::auth), custom login flows can be
+/// plugged in by implementing this trait. Implementations usually finish by calling
+/// [`Session::check_status_at`] to report the status after login.
+///
+/// Requests sent by [`Session::execute`] and [`Session::execute_idempotent`] go through
+/// the [`RateLimiter`] and the [`RetryPolicy`] of the session, as the built-in methods do.
+///
+/// # Examples
+///
+/// ```
+/// use neust::{async_trait, AuthMethod, Endpoint, Result, Session, UserStatus};
+///
+/// struct RawCookie(String);
+///
+/// #[async_trait]
+/// impl AuthMethod for RawCookie {
+///     async fn execute(&self, session: &Session, endpoint: &Endpoint) -> Result<UserStatus> {
+///         session.cookie_jar().add_cookie_str(
+///             &format!("{}={}", endpoint.cookie_name(), self.0),
+///             endpoint.cookie_url(),
+///         );
+///         session.check_status_at(endpoint).await
+///     }
+/// }
+/// ```
 #[async_trait]
 pub trait AuthMethod {
     /// Execute auth process.
@@ -36,7 +60,10 @@ pub trait AuthMethod {
 #[derive(Debug, Clone)]
 pub struct Session {
     client: Client,
-    cookie_jar: Arc<Jar>,
+    transport: Arc<dyn Transport>,
+    cookie_jar: Arc<CookieJar>,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl Session {
@@ -58,7 +85,7 @@ impl Session {
         &self.client
     }
 
-    /// Get internal [`Jar`].
+    /// Get internal [`CookieJar`].
     ///
     /// # Warn
     ///
@@ -75,9 +102,53 @@ impl Session {
     /// let url = Url::parse("https://example.com").unwrap();
     /// cookie_jar.add_cookie_str("some_new_cookie=123", &url);
     /// ```
-    pub fn cookie_jar(&self) -> &Jar {
+    pub fn cookie_jar(&self) -> &CookieJar {
         &self.cookie_jar
     }
+
+    /// Get the [`RetryPolicy`] of idempotent steps in auth flows.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Get the [`RateLimiter`] consulted before requests of auth flows.
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    /// Execute a request of auth flows, waiting for the [`RateLimiter`] if any.
+    ///
+    /// It's never retried, use [`Session::execute_idempotent`] for requests that can be
+    /// repeated safely.
+    pub async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+        if let (Some(limiter), Some(host)) = (&self.rate_limiter, request.url.host_str()) {
+            limiter.acquire(host).await;
+        }
+
+        self.transport.execute(request).await
+    }
+
+    /// Drive `flow` to the end, performing its steps.
+    pub(crate) async fn drive(&self, mut flow: impl Flow + Send) -> Result<UserStatus> {
+        let mut input = Input::Start;
+
+        loop {
+            input = match flow.resume(input)? {
+                Step::Send {
+                    request,
+                    idempotent: true,
+                } => Input::Response(self.execute_idempotent(request).await?),
+                Step::Send {
+                    request,
+                    idempotent: false,
+                } => Input::Response(self.execute(request).await?),
+                Step::Solve { solver, challenge } => {
+                    Input::Answer(solver.0.solve(&challenge).await)
+                }
+                Step::Done(status) => return Ok(status),
+            }
+        }
+    }
 }
 
 impl AsRef<Client> for Session {
@@ -102,9 +173,11 @@ impl Session {
 impl Session {
     /// Customize the client inside created [`Session`].
     ///
-    /// Changes on [cookie_provider](crate::reqwest::ClientBuilder::cookie_provider) and
-    /// [cookie_store](crate::reqwest::ClientBuilder::cookie_store)
-    /// will be ignored.
+    /// Changes on [cookie_provider](crate::reqwest::ClientBuilder::cookie_provider),
+    /// [cookie_store](crate::reqwest::ClientBuilder::cookie_store) and
+    /// [redirect](crate::reqwest::ClientBuilder::redirect) will be ignored.
+    /// Redirects are followed up to 10 times, except in operations like
+    /// [`Session::service_ticket`] that inspect redirects themselves.
     ///
     /// # Examples
     ///
@@ -119,32 +192,119 @@ impl Session {
     where
         B: FnOnce(ClientBuilder) -> ClientBuilder,
     {
-        static UA: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+        let cookie_jar = Arc::new(CookieJar::default());
 
-        let cookie_jar = Arc::new(Jar::default());
-
-        let client = build(ClientBuilder::new().user_agent(UA))
+        let client = build(ClientBuilder::new().user_agent(flow::USER_AGENT))
+            .redirect(transport::redirect_policy())
             .cookie_provider(cookie_jar.clone())
             .build()
             .expect("cannot initialize TLS backend, or cannot load the system configuration");
 
-        Session { client, cookie_jar }
+        Session {
+            client: client.clone(),
+            transport: Arc::new(ReqwestTransport { client }),
+            cookie_jar,
+            retry_policy: RetryPolicy::none(),
+            rate_limiter: None,
+        }
+    }
+
+    /// Retry idempotent steps in auth flows under `policy`.
+    ///
+    /// See [`RetryPolicy`] for which steps are retried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use neust::{RetryPolicy, Session};
+    /// let session = Session::new().with_retry_policy(RetryPolicy::default());
+    /// ```
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Wait for `limiter` before requests of auth flows to the limited hosts.
+    ///
+    /// The same limiter can be shared by many sessions to limit requests from the host
+    /// as a whole, see [`RateLimiter`].
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Creates a [`Session`] sending requests of auth flows through a scripted transport.
+    #[cfg(test)]
+    pub(crate) fn with_transport(transport: Arc<crate::transport::mock::MockTransport>) -> Self {
+        let cookie_jar = transport.cookie_jar();
+
+        let client = ClientBuilder::new()
+            .cookie_provider(cookie_jar.clone())
+            .build()
+            .unwrap();
+
+        Session {
+            client,
+            transport,
+            cookie_jar,
+            retry_policy: RetryPolicy::none(),
+            rate_limiter: None,
+        }
     }
 }
 
 impl Session {
-    pub(crate) async fn _check_status(&self, endpoint: &Endpoint) -> Result<UserStatus> {
-        let request = self.client.get(endpoint.login_url).build()?;
+    /// Login to the CAS via a custom [`Endpoint`].
+    ///
+    /// Behaves the same as [`Session::login`] except for the endpoint.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::auth;
+    /// # use neust::{Endpoint, Session};
+    /// let endpoint = Endpoint::builder("http://127.0.0.1:8080/tpass/").build()?;
+    /// let session = Session::new();
+    /// let credential = auth::Credential::new("username", "password");
+    /// let status = session.login_at(&endpoint, &credential).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn login_at<A: AuthMethod>(
+        &self,
+        endpoint: &Endpoint,
+        auth: &A,
+    ) -> Result<UserStatus> {
+        auth.execute(self, endpoint).await
+    }
 
-        let response_body = self.client.execute(request).await?.text().await?;
+    /// Login to the CAS via a custom [`Endpoint`], replacing the user that already logged in.
+    ///
+    /// Behaves the same as [`Session::relogin`] except for the endpoint.
+    pub async fn relogin_at<A: AuthMethod>(
+        &self,
+        endpoint: &Endpoint,
+        auth: &A,
+    ) -> Result<UserStatus> {
+        self.cookie_jar
+            .remove_cookies(endpoint.cookie_url(), endpoint.auth_cookie_names());
 
-        let token = self
-            .cookie_jar
-            .cookies(&endpoint.cookie_url)
-            .and_then(|h| h.to_str().map(|s| s.to_owned()).ok())
-            .and_then(|s| find_cookie_value(&s, endpoint.cookie_name));
+        auth.execute(self, endpoint).await
+    }
 
-        Ok(UserStatus::from_response_html(&response_body, token))
+    /// Check user status on the CAS via a custom [`Endpoint`] in the session.
+    ///
+    /// Behaves the same as [`Session::check_status`] except for the endpoint.
+    pub async fn check_status_at(&self, endpoint: &Endpoint) -> Result<UserStatus> {
+        self.drive(CheckStatus::new(&self.cookie_jar, endpoint))
+            .await
+    }
+
+    /// Logout from the CAS via a custom [`Endpoint`].
+    ///
+    /// Behaves the same as [`Session::logout`] except for the endpoint.
+    pub async fn logout_at(&self, endpoint: &Endpoint) -> Result<()> {
+        flow::ensure_logged_out(self.drive(Logout::new(&self.cookie_jar, endpoint)).await?)
     }
 }
 
@@ -158,6 +318,7 @@ impl Session {
     /// login won't clear the auth-related cookies every time it is called.
     ///
     /// This is an intentionally reserved **feature** to avoid the reuse of sessions.
+    /// To replace the logged-in user explicitly, use [`Session::relogin`].
     ///
     /// # Example
     /// ```no_run
@@ -169,14 +330,38 @@ impl Session {
     /// let status = session.login(&credential).await?;
     /// match status {
     ///     UserStatus::Active { username, .. } => println!("{}", username),
-    ///     UserStatus::Rejected => println!("wrong username or password"),
+    ///     UserStatus::Rejected { reason: Some(reason) } => println!("rejected: {}", reason),
     ///     _ => println!("something wrong: {:?}", status)
     /// };
     /// # Ok(())
     /// # }
     /// ```
     pub async fn login<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
-        auth.execute(self, &ENDPOINT_DIRECT).await
+        self.login_at(&ENDPOINT_DIRECT, auth).await
+    }
+
+    /// Login to the CAS via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint),
+    /// replacing the user that already logged in.
+    ///
+    /// Unlike [`Session::login`], the auth-related cookies of the endpoint are removed
+    /// before login, so it can be used to switch accounts or to recover from an expired token
+    /// without creating a new [`Session`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::auth;
+    /// # use neust::Session;
+    /// let session = Session::new();
+    /// session.login(&auth::Credential::new("username_a", "password_a")).await?;
+    /// let status = session
+    ///     .relogin(&auth::Credential::new("username_b", "password_b"))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn relogin<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
+        self.relogin_at(&ENDPOINT_DIRECT, auth).await
     }
 
     /// Check user status on the CAS via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint)
@@ -193,7 +378,30 @@ impl Session {
     /// # }
     /// ```
     pub async fn check_status(&self) -> Result<UserStatus> {
-        self._check_status(&ENDPOINT_DIRECT).await
+        self.check_status_at(&ENDPOINT_DIRECT).await
+    }
+
+    /// Logout from the CAS via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint)
+    /// and clear the auth-related cookies in the session.
+    ///
+    /// Returns [`Error::HttpStatus`](crate::error::Error::HttpStatus) without clearing cookies
+    /// if the CAS fails to logout, or [`Error::StatusConflict`](crate::error::Error::StatusConflict)
+    /// if the user is still online after logout.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::auth;
+    /// # use neust::Session;
+    /// let session = Session::new();
+    /// let credential = auth::Credential::new("username", "password");
+    /// session.login(&credential).await?;
+    /// session.logout().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn logout(&self) -> Result<()> {
+        self.logout_at(&ENDPOINT_DIRECT).await
     }
 }
 
@@ -229,7 +437,20 @@ impl Session {
     /// [`WebVPNEndpoint`]: crate::doc::endpoint::WebVPNEndpoint
     /// [`Error::StatusConflict`]: crate::error::Error::StatusConflict
     pub async fn login_via_webvpn<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
-        auth.execute(self, &ENDPOINT_WEBVPN).await
+        self.login_at(&ENDPOINT_WEBVPN, auth).await
+    }
+
+    /// Login to the CAS via [`WebVPNEndpoint`], replacing the user that already
+    /// logged in via [`WebVPNEndpoint`].
+    ///
+    /// Caller should still ensure that there is **one** user logged in via [`DirectEndpoint`].
+    ///
+    /// See also [`Session::relogin`] and [documentation for endpoints](crate::doc::endpoint).
+    ///
+    /// [`DirectEndpoint`]: crate::doc::endpoint::DirectEndpoint
+    /// [`WebVPNEndpoint`]: crate::doc::endpoint::WebVPNEndpoint
+    pub async fn relogin_via_webvpn<A: AuthMethod>(&self, auth: &A) -> Result<UserStatus> {
+        self.relogin_at(&ENDPOINT_WEBVPN, auth).await
     }
 
     /// Check user status on the CAS via
@@ -247,39 +468,169 @@ impl Session {
     /// ```
     /// See also [documentation for endpoints](crate::doc::endpoint).
     pub async fn check_status_via_webvpn(&self) -> Result<UserStatus> {
-        self._check_status(&ENDPOINT_WEBVPN).await
+        self.check_status_at(&ENDPOINT_WEBVPN).await
     }
-}
 
-fn find_cookie_value(raw: &str, cookie_name: &str) -> Option<String> {
-    match raw.find(cookie_name) {
-        None => None,
-        Some(i) => {
-            let start_index = i + cookie_name.len() + 1;
-            let sub = &raw[start_index..];
-            Some(match sub.find(';') {
-                None => sub.to_owned(),
-                Some(end_index) => sub[..end_index].to_owned(),
-            })
-        }
+    /// Logout from the CAS via [`WebVPNEndpoint`](crate::doc::endpoint::WebVPNEndpoint)
+    /// and clear the auth-related cookies in the session.
+    ///
+    /// The user logged in via [`DirectEndpoint`](crate::doc::endpoint::DirectEndpoint)
+    /// is not affected, use [`Session::logout`] to logout it as well.
+    ///
+    /// Returns [`Error::HttpStatus`](crate::error::Error::HttpStatus) without clearing cookies
+    /// if the CAS fails to logout, or [`Error::StatusConflict`](crate::error::Error::StatusConflict)
+    /// if the user is still online after logout.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::Session;
+    /// # let session = Session::new();
+    /// session.logout_via_webvpn().await?;
+    /// session.logout().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    /// See also [documentation for endpoints](crate::doc::endpoint).
+    pub async fn logout_via_webvpn(&self) -> Result<()> {
+        self.logout_at(&ENDPOINT_WEBVPN).await
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::session::find_cookie_value;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use reqwest::{cookie::CookieStore, header::SET_COOKIE, Method, StatusCode, Url};
+
+    use crate::endpoint::Endpoint;
+    use crate::error::{Error, Result};
+    use crate::retry::RetryPolicy;
+    use crate::session::{AuthMethod, Session};
+    use crate::status::UserStatus;
+    use crate::transport::mock::{MockResponse, MockTransport};
+    use crate::transport::HttpRequest;
 
     #[test]
-    fn test_find_cookie_value() {
-        let table = vec![
-            ("refresh=1; wengine_vpn_ticketwebvpn_neu_edu_cn=3c2cca8a854e8122", "wengine_vpn_ticketwebvpn_neu_edu_cn", Some("3c2cca8a854e8122".to_owned())),
-            ("CASTGC=TGT-20180000-1827000-izbHeCI9y53RyIpMoYKxKbdyjtkgmfOy0NwbJHHiwXQabRYYKK-tpass; Language=zh_CN; jsessionid_tpass=ZLr9vBLe0xcX0nPsDfv3WASFiziyH-sMuy4CDoiIcqJkASjw136y!-1701433832", "CASTGC", Some("TGT-20180000-1827000-izbHeCI9y53RyIpMoYKxKbdyjtkgmfOy0NwbJHHiwXQabRYYKK-tpass".to_owned())),
-            ("CASTGC=TGT-20180000-1827000-izbHeCI9y53RyIpMoYKxKbdyjtkgmfOy0NwbJHHiwXQabRYYKK-tpass; Language=zh_CN; jsessionid_tpass=ZLr9vBLe0xcX0nPsDfv3WASFiziyH-sMuy4CDoiIcqJkASjw136y!-1701433832", "jsessionid_tpass", Some("ZLr9vBLe0xcX0nPsDfv3WASFiziyH-sMuy4CDoiIcqJkASjw136y!-1701433832".to_owned())),
-            ("", "wengine_vpn_ticketwebvpn_neu_edu_cn", None),
-        ];
-
-        for (raw, name, expected) in table {
-            assert_eq!(find_cookie_value(raw, name), expected)
+    fn test_with_client_builder() {
+        let user_agent = String::from("neust-test");
+        let session = Session::with_client_builder(move |builder| builder.user_agent(user_agent));
+        assert!(session.cookie_jar().unexpired_cookies().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_logout() {
+        let login_page = "<html><head><title>智慧东大--统一身份认证</title></head></html>";
+        let portal = "<html><head><title>个人中心</title></head></html>";
+
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/logout",
+                    MockResponse::ok("").header(SET_COOKIE, "Language=zh_CN; Path=/tpass/"),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    MockResponse::ok(login_page),
+                ),
+        );
+        let session = Session::with_transport(transport.clone());
+        session.cookie_jar().add_cookie_str(
+            "CASTGC=TGT-20180000-1-tpass; Path=/tpass/",
+            &Url::parse("https://pass.neu.edu.cn/tpass/").unwrap(),
+        );
+
+        session.logout().await.unwrap();
+        transport.assert_done();
+        assert_eq!(
+            transport.cookies()[0].as_deref(),
+            Some("CASTGC=TGT-20180000-1-tpass")
+        );
+        assert_eq!(transport.cookies()[1].as_deref(), Some("Language=zh_CN"));
+
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/logout",
+                    MockResponse::ok(""),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    MockResponse::ok(portal),
+                ),
+        );
+        let session = Session::with_transport(transport.clone());
+
+        assert!(matches!(session.logout().await, Err(Error::StatusConflict)));
+        transport.assert_done();
+
+        let transport = Arc::new(MockTransport::new().expect(
+            Method::GET,
+            "https://pass.neu.edu.cn/tpass/logout",
+            MockResponse::ok("").status(StatusCode::NOT_FOUND),
+        ));
+        let session = Session::with_transport(transport.clone());
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/").unwrap();
+        session
+            .cookie_jar()
+            .add_cookie_str("CASTGC=TGT-20180000-1-tpass; Path=/tpass/", &url);
+
+        assert!(matches!(
+            session.logout().await,
+            Err(Error::HttpStatus {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+        transport.assert_done();
+        assert!(session.cookie_jar().cookies(&url).is_some());
+    }
+
+    struct Ping;
+
+    #[async_trait]
+    impl AuthMethod for Ping {
+        async fn execute(&self, session: &Session, endpoint: &Endpoint) -> Result<UserStatus> {
+            let url = Url::parse("https://pass.neu.edu.cn/tpass/ping").unwrap();
+            let response = session.execute_idempotent(HttpRequest::get(url)).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            session.check_status_at(endpoint).await
         }
     }
+
+    #[tokio::test]
+    async fn test_custom_auth_method() {
+        let login_page = "<html><head><title>智慧东大--统一身份认证</title></head></html>";
+        let transport = Arc::new(
+            MockTransport::new()
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/ping",
+                    MockResponse::ok("").status(StatusCode::SERVICE_UNAVAILABLE),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/ping",
+                    MockResponse::ok("pong"),
+                )
+                .expect(
+                    Method::GET,
+                    "https://pass.neu.edu.cn/tpass/login",
+                    MockResponse::ok(login_page),
+                ),
+        );
+        let session = Session::with_transport(transport.clone()).with_retry_policy(RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        });
+
+        assert!(session.login(&Ping).await.unwrap().is_rejected());
+        transport.assert_done();
+    }
 }
@@ -0,0 +1,200 @@
+#[cfg(feature = "serde")]
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::{BufReader, BufWriter, Read, Write};
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+use cookie_store::Cookie;
+
+#[cfg(feature = "serde")]
+use crate::error::{Error, Result};
+use crate::session::Session;
+
+/// A copy of the cookie state in a [`Session`].
+///
+/// It contains all unexpired cookies, including the auth-related cookies
+/// of both [`DirectEndpoint`] and [`WebVPNEndpoint`], with their domains, paths and expiry.
+///
+/// With feature **serde**, it can be serialized as a list of cookies, and be saved to and
+/// loaded from JSON files by [`Session::save_to`] and [`Session::load_from`].
+///
+/// [`DirectEndpoint`]: crate::doc::endpoint::DirectEndpoint
+/// [`WebVPNEndpoint`]: crate::doc::endpoint::WebVPNEndpoint
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SessionSnapshot {
+    cookies: Vec<Cookie<'static>>,
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl SessionSnapshot {
+    /// Write the snapshot to `writer` in JSON format.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer(writer, &self.cookies).map_err(Error::snapshot_error)
+    }
+
+    /// Read a snapshot written by [`SessionSnapshot::write_to`] from `reader`.
+    ///
+    /// Cookies that have expired since the snapshot was taken are dropped.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self> {
+        let cookies: Vec<Cookie<'static>> =
+            serde_json::from_reader(reader).map_err(Error::snapshot_error)?;
+
+        Ok(SessionSnapshot {
+            cookies: cookies.into_iter().filter(|c| !c.is_expired()).collect(),
+        })
+    }
+}
+
+impl Session {
+    /// Take a [`SessionSnapshot`] of the cookies in the session.
+    pub fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            cookies: self.cookie_jar().unexpired_cookies(),
+        }
+    }
+
+    /// Replace all cookies in the session with those in `snapshot`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use neust::Session;
+    /// let session_a = Session::new();
+    /// let session_b = Session::new();
+    /// session_b.restore(&session_a.snapshot());
+    /// ```
+    pub fn restore(&self, snapshot: &SessionSnapshot) {
+        self.cookie_jar().replace_cookies(&snapshot.cookies);
+    }
+
+    /// Save the cookies in the session to the file at `path` in JSON format,
+    /// so that it can be loaded by [`Session::load_from`] after the process restarts.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::{Session, auth};
+    /// let session = Session::new();
+    /// let credential = auth::Credential::new("username", "password");
+    /// if session.login(&credential).await?.is_active() {
+    ///     session.save_to("session.json")?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.snapshot().write_to(&mut writer)?;
+        Ok(writer.flush()?)
+    }
+
+    /// Creates a [`Session`] using default configurations
+    /// and restores the cookies saved by [`Session::save_to`].
+    ///
+    /// To customize the client, use [`Session::with_client_builder`] and [`Session::restore`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn doc() -> Result<(), neust::Error> {
+    /// # use neust::Session;
+    /// let session = Session::load_from("session.json")?;
+    /// let status = session.check_status().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Session> {
+        let snapshot = SessionSnapshot::read_from(BufReader::new(File::open(path)?))?;
+        let session = Session::new();
+        session.restore(&snapshot);
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{cookie::CookieStore, Url};
+
+    use crate::session::Session;
+    #[cfg(feature = "serde")]
+    use crate::snapshot::SessionSnapshot;
+
+    #[test]
+    fn test_snapshot_restore() {
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/").unwrap();
+        let session = Session::new();
+        session
+            .cookie_jar()
+            .add_cookie_str("CASTGC=TGT-20180000-tpass; Path=/tpass/", &url);
+
+        let restored = Session::new();
+        restored.restore(&session.snapshot());
+        let cookies = restored.cookie_jar().cookies(&url).unwrap();
+        assert_eq!(cookies.to_str().unwrap(), "CASTGC=TGT-20180000-tpass");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_round_trip() {
+        let direct_url = Url::parse("https://pass.neu.edu.cn/tpass/").unwrap();
+        let webvpn_url = Url::parse("https://webvpn.neu.edu.cn/").unwrap();
+
+        let session = Session::new();
+        let jar = session.cookie_jar();
+        jar.add_cookie_str("CASTGC=TGT-20180000-tpass; Path=/tpass/", &direct_url);
+        jar.add_cookie_str(
+            "wengine_vpn_ticketwebvpn_neu_edu_cn=3c2cca8a854e8122; Path=/; Max-Age=3600",
+            &webvpn_url,
+        );
+        jar.add_cookie_str("expired=1; Max-Age=0", &direct_url);
+
+        let mut buf = Vec::new();
+        session.snapshot().write_to(&mut buf).unwrap();
+        let snapshot = SessionSnapshot::read_from(buf.as_slice()).unwrap();
+
+        let restored = Session::new();
+        restored.restore(&snapshot);
+        let jar = restored.cookie_jar();
+
+        let direct = jar.cookies(&direct_url).unwrap();
+        assert_eq!(direct.to_str().unwrap(), "CASTGC=TGT-20180000-tpass");
+
+        let webvpn = jar.cookies(&webvpn_url).unwrap();
+        assert_eq!(
+            webvpn.to_str().unwrap(),
+            "wengine_vpn_ticketwebvpn_neu_edu_cn=3c2cca8a854e8122"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_serde() {
+        let session = Session::new();
+        let url = Url::parse("https://pass.neu.edu.cn/tpass/").unwrap();
+        session
+            .cookie_jar()
+            .add_cookie_str("CASTGC=TGT-20180000-tpass; Path=/tpass/", &url);
+
+        let mut buf = Vec::new();
+        session.snapshot().write_to(&mut buf).unwrap();
+        assert_eq!(serde_json::to_vec(&session.snapshot()).unwrap(), buf);
+
+        let snapshot: SessionSnapshot = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(snapshot.cookies.len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_malformed() {
+        assert!(SessionSnapshot::read_from("not json".as_bytes()).is_err());
+    }
+}
@@ -1,10 +1,11 @@
 use std::fmt::{Display, Formatter};
 
-use lazy_static::lazy_static;
-use regex::Regex;
+use crate::auth::CaptchaChallenge;
+use crate::page::{self, PageKind};
 
 /// The endpoint-specific user status in a [`Session`](crate::session::Session).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum UserStatus {
     /// User is online and the account is active.
@@ -40,7 +41,77 @@ pub enum UserStatus {
     /// As a result of check status action, it may mean:
     /// - the user session is expired
     /// - no user has logged in
-    Rejected,
+    ///
+    /// # Breaking
+    ///
+    /// It used to be a unit variant, match it by `UserStatus::Rejected { .. }` instead.
+    Rejected {
+        /// Why the login request is rejected, [`None`] if the CAS does not tell,
+        /// e.g. when checking the status of a session that no user has logged in.
+        reason: Option<RejectReason>,
+    },
+}
+
+/// The reason why a login request is rejected.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum RejectReason {
+    /// The username or password is wrong.
+    WrongCredentials,
+    /// The account is locked.
+    AccountLocked,
+    /// The CAS requires a captcha to login.
+    CaptchaRequired {
+        /// The captcha fetched from the login page, which can be answered by
+        /// [`Credential::with_captcha_answer`](crate::auth::Credential::with_captcha_answer).
+        ///
+        /// It's [`None`] if the captcha is only mentioned by the error message.
+        challenge: Option<CaptchaChallenge>,
+    },
+    /// Too many failed attempts in a short time.
+    TooManyAttempts,
+    /// The token is expired or invalid.
+    TokenExpired,
+    /// Wechat has not authorized the login request yet.
+    PendingWechatAuthorization,
+    /// Other reasons with the raw message shown by the CAS.
+    Unknown {
+        /// The raw message shown by the CAS.
+        message: String,
+    },
+}
+
+impl Display for RejectReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectReason::WrongCredentials => write!(f, "wrong credentials"),
+            RejectReason::AccountLocked => write!(f, "account locked"),
+            RejectReason::CaptchaRequired { .. } => write!(f, "captcha required"),
+            RejectReason::TooManyAttempts => write!(f, "too many attempts"),
+            RejectReason::TokenExpired => write!(f, "token expired"),
+            RejectReason::PendingWechatAuthorization => write!(f, "pending wechat authorization"),
+            RejectReason::Unknown { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl RejectReason {
+    pub(crate) fn from_message(message: &str) -> RejectReason {
+        if message.contains("锁定") {
+            RejectReason::AccountLocked
+        } else if message.contains("验证码") {
+            RejectReason::CaptchaRequired { challenge: None }
+        } else if message.contains("次数") || message.contains("频繁") {
+            RejectReason::TooManyAttempts
+        } else if message.contains("密码错误") || message.contains("密码不正确") {
+            RejectReason::WrongCredentials
+        } else {
+            RejectReason::Unknown {
+                message: message.to_owned(),
+            }
+        }
+    }
 }
 
 impl Display for UserStatus {
@@ -49,7 +120,10 @@ impl Display for UserStatus {
             UserStatus::Active { username, .. } => write!(f, "active#{}", username),
             UserStatus::NeedReset { .. } => write!(f, "need reset"),
             UserStatus::Banned { .. } => write!(f, "banned"),
-            UserStatus::Rejected => write!(f, "rejected"),
+            UserStatus::Rejected { reason: None } => write!(f, "rejected"),
+            UserStatus::Rejected {
+                reason: Some(reason),
+            } => write!(f, "rejected: {}", reason),
         }
     }
 }
@@ -64,7 +138,7 @@ impl UserStatus {
     /// let x = UserStatus::Active { username: "".to_owned(), token: "".to_owned() };
     /// assert_eq!(x.is_active(), true);
     ///
-    /// let x = UserStatus::Rejected;
+    /// let x = UserStatus::Rejected { reason: None };
     /// assert_eq!(x.is_active(), false);
     /// ```
     pub fn is_active(&self) -> bool {
@@ -76,15 +150,37 @@ impl UserStatus {
     /// # Examples
     ///
     /// ```
-    /// # use neust::UserStatus;
-    /// let x = UserStatus::Rejected;
+    /// # use neust::{RejectReason, UserStatus};
+    /// let x = UserStatus::Rejected { reason: None };
+    /// assert_eq!(x.is_rejected(), true);
+    ///
+    /// let x = UserStatus::Rejected { reason: Some(RejectReason::WrongCredentials) };
     /// assert_eq!(x.is_rejected(), true);
     ///
     /// let x = UserStatus::Active { username: "".to_owned(), token: "".to_owned() };
     /// assert_eq!(x.is_rejected(), false);
     /// ```
     pub fn is_rejected(&self) -> bool {
-        matches!(self, UserStatus::Rejected)
+        matches!(self, UserStatus::Rejected { .. })
+    }
+
+    /// Get the reason iff the status is [`Rejected`](UserStatus::Rejected) for a known reason.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use neust::{RejectReason, UserStatus};
+    /// let x = UserStatus::Rejected { reason: Some(RejectReason::AccountLocked) };
+    /// assert_eq!(x.get_reject_reason(), Some(&RejectReason::AccountLocked));
+    ///
+    /// let x = UserStatus::Rejected { reason: None };
+    /// assert_eq!(x.get_reject_reason(), None);
+    /// ```
+    pub fn get_reject_reason(&self) -> Option<&RejectReason> {
+        match self {
+            UserStatus::Rejected { reason } => reason.as_ref(),
+            _ => None,
+        }
     }
 
     /// Get the username iff the status is [`Active`](UserStatus::Active).
@@ -99,7 +195,7 @@ impl UserStatus {
     /// let x = UserStatus::Banned { token: "".to_owned() };
     /// assert!(matches!(x.get_username(), None));
     ///
-    /// let x = UserStatus::Rejected;
+    /// let x = UserStatus::Rejected { reason: None };
     /// assert!(matches!(x.get_username(), None));
     /// ```
     pub fn get_username(&self) -> Option<&str> {
@@ -122,7 +218,7 @@ impl UserStatus {
     /// let x = UserStatus::Banned { token: "".to_owned() };
     /// assert!(matches!(x.get_token(), Some(_)));
     ///
-    /// let x = UserStatus::Rejected;
+    /// let x = UserStatus::Rejected { reason: None };
     /// assert!(matches!(x.get_token(), None));
     /// ```
     pub fn get_token(&self) -> Option<&str> {
@@ -136,29 +232,132 @@ impl UserStatus {
 }
 
 impl UserStatus {
+    /// Attach `reason` to [`Rejected`](UserStatus::Rejected) status without a reason.
+    pub(crate) fn with_reject_reason(self, reason: RejectReason) -> UserStatus {
+        match self {
+            UserStatus::Rejected { reason: None } => UserStatus::Rejected {
+                reason: Some(reason),
+            },
+            status => status,
+        }
+    }
+
     pub(crate) fn from_response_html(html: &str, token: Option<String>) -> UserStatus {
-        lazy_static! {
-            static ref TITLE_RE: Regex = Regex::new(r"<title>(.+?)</title>").unwrap();
-            static ref USERNAME_RE: Regex = Regex::new(r#"var id_number = "(.+?)""#).unwrap();
+        let token = token.unwrap_or_else(|| "".into());
+
+        match page::kind(html) {
+            PageKind::Login => UserStatus::Rejected {
+                reason: page::error_message(html).map(RejectReason::from_message),
+            },
+            PageKind::WebVpnLogin => UserStatus::Rejected { reason: None },
+            PageKind::NeedReset => UserStatus::NeedReset { token },
+            PageKind::Banned => UserStatus::Banned { token },
+            _ => UserStatus::Active {
+                token,
+                username: page::username(html).unwrap_or_default().to_owned(),
+            },
         }
+    }
+}
 
-        let title = TITLE_RE
-            .captures(html)
-            .and_then(|cap| cap.get(1).map(|s| s.as_str()));
+#[cfg(test)]
+mod tests {
+    use crate::status::{RejectReason, UserStatus};
 
-        let username = USERNAME_RE
-            .captures(html)
-            .and_then(|cap| cap.get(1).map(|s| s.as_str()))
-            .map(|s| s.to_owned())
-            .unwrap_or_else(|| "".to_owned());
+    #[test]
+    fn test_reject_reason_from_response_html() {
+        let page = |message: &str| {
+            format!(
+                r#"<html><head><title>智慧东大--统一身份认证</title></head>
+<body><span id="errormsg" class="errormsg">{}</span></body></html>"#,
+                message
+            )
+        };
 
-        let token = token.unwrap_or_else(|| "".into());
+        let table = vec![
+            ("", None),
+            ("用户名或密码错误", Some(RejectReason::WrongCredentials)),
+            (
+                "账号已被锁定，请稍后再试",
+                Some(RejectReason::AccountLocked),
+            ),
+            (
+                "请输入验证码",
+                Some(RejectReason::CaptchaRequired { challenge: None }),
+            ),
+            ("登录失败次数过多", Some(RejectReason::TooManyAttempts)),
+            (
+                "系统繁忙",
+                Some(RejectReason::Unknown {
+                    message: "系统繁忙".to_owned(),
+                }),
+            ),
+        ];
+
+        for (message, expected) in table {
+            let status = UserStatus::from_response_html(&page(message), None);
+            assert!(status.is_rejected());
+            assert_eq!(status.get_reject_reason(), expected.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_status_from_fixtures() {
+        let table = vec![
+            (include_str!("../tests/fixtures/login.html"), "rejected"),
+            (
+                include_str!("../tests/fixtures/login_error_password.html"),
+                "rejected: wrong credentials",
+            ),
+            (
+                include_str!("../tests/fixtures/portal.html"),
+                "active#20180000",
+            ),
+            (
+                include_str!("../tests/fixtures/need_reset.html"),
+                "need reset",
+            ),
+            (include_str!("../tests/fixtures/banned.html"), "banned"),
+            (
+                include_str!("../tests/fixtures/webvpn_login.html"),
+                "rejected",
+            ),
+        ];
+
+        for (html, expected) in table {
+            let status = UserStatus::from_response_html(html, Some("TGT-1-tpass".to_owned()));
+            assert_eq!(status.to_string(), expected);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_user_status_serde() {
+        let table = vec![
+            (
+                UserStatus::Active {
+                    token: "TGT-1-tpass".to_owned(),
+                    username: "20180000".to_owned(),
+                },
+                r#"{"Active":{"token":"TGT-1-tpass","username":"20180000"}}"#,
+            ),
+            (
+                UserStatus::Rejected { reason: None },
+                r#"{"Rejected":{"reason":null}}"#,
+            ),
+            (
+                UserStatus::Rejected {
+                    reason: Some(RejectReason::WrongCredentials),
+                },
+                r#"{"Rejected":{"reason":"WrongCredentials"}}"#,
+            ),
+        ];
 
-        match title {
-            Some("智慧东大--统一身份认证") => UserStatus::Rejected,
-            Some("智慧东大") => UserStatus::NeedReset { token },
-            Some("系统提示") => UserStatus::Banned { token },
-            _ => UserStatus::Active { token, username },
+        for (status, expected) in table {
+            let json = serde_json::to_string(&status).unwrap();
+            assert_eq!(json, expected);
+            let restored: UserStatus = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored.to_string(), status.to_string());
         }
     }
 }
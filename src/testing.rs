@@ -0,0 +1,640 @@
+//! Provide a local mock of the CAS and the WebVPN for integration tests.
+//!
+//! [`TestServer`] emulates pages and cookies that [`Session`](crate::Session) relies on,
+//! so that code using neust can be tested without access to the real CAS.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use neust::testing::TestServer;
+//! use neust::{auth, Session};
+//!
+//! let server = TestServer::start().await?;
+//! server.add_account("20180000", "password");
+//!
+//! let session = Session::new();
+//! let credential = auth::Credential::new("20180000", "password");
+//! let status = session.login_at(&server.endpoint(), &credential).await?;
+//! assert_eq!(status.get_username(), Some("20180000"));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+
+use hyper::header::{HeaderValue, CONTENT_TYPE, COOKIE, LOCATION, SET_COOKIE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use reqwest::Url;
+use tokio::sync::oneshot;
+
+use crate::endpoint::Endpoint;
+
+/// Name of the ticket cookie of the WebVPN.
+static WEBVPN_COOKIE_NAME: &str = "wengine_vpn_ticketwebvpn_neu_edu_cn";
+
+/// States of accounts in [`TestServer`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AccountState {
+    /// Logs in normally.
+    Active,
+    /// Logs in, but the password has to be reset before using services.
+    NeedReset,
+    /// Logs in, but is not allowed to use services.
+    Banned,
+    /// Fails to log in because the account is locked.
+    Locked,
+}
+
+/// A local HTTP server emulating the CAS and the WebVPN.
+///
+/// - the CAS is served under `/tpass/`, see [`TestServer::endpoint`]
+/// - the CAS via the WebVPN is served under `/webvpn/tpass/`, see
+///   [`TestServer::webvpn_endpoint`]
+///
+/// Accounts, captcha and failures are scripted through methods of the server.
+/// The server is shut down when dropped.
+#[derive(Debug)]
+pub struct TestServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl TestServer {
+    /// Starts a server listening on a random local port.
+    ///
+    /// It must be called in the context of a tokio runtime.
+    pub async fn start() -> std::io::Result<TestServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request))) }
+        });
+
+        let (shutdown, receiver) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)
+            .expect("fail to register the listener, not in the context of a tokio runtime")
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                receiver.await.ok();
+            });
+        tokio::spawn(server);
+
+        Ok(TestServer {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Get the base url of the server, e.g. `http://127.0.0.1:8080/`.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}/", self.addr)).unwrap()
+    }
+
+    /// Get the [`Endpoint`] of the emulated CAS.
+    pub fn endpoint(&self) -> Endpoint {
+        Endpoint::builder(format!("{}tpass/", self.url()))
+            .build()
+            .unwrap()
+    }
+
+    /// Get the [`Endpoint`] of the emulated CAS via the WebVPN, which authenticates
+    /// users by the ticket cookie of the WebVPN.
+    pub fn webvpn_endpoint(&self) -> Endpoint {
+        Endpoint::builder(format!("{}webvpn/tpass/", self.url()))
+            .logout_url(format!("{}webvpn/logout", self.url()))
+            .cookie_name(WEBVPN_COOKIE_NAME)
+            .auth_cookie_names([WEBVPN_COOKIE_NAME])
+            .cookie_url(self.url())
+            .build()
+            .unwrap()
+    }
+
+    /// Add an [`AccountState::Active`] account, replacing the existing one.
+    pub fn add_account(&self, username: &str, password: &str) {
+        self.state().accounts.insert(
+            username.to_owned(),
+            Account {
+                password: password.to_owned(),
+                state: AccountState::Active,
+            },
+        );
+    }
+
+    /// Change the state of an existing account.
+    pub fn set_account_state(&self, username: &str, state: AccountState) {
+        if let Some(account) = self.state().accounts.get_mut(username) {
+            account.state = state;
+        }
+    }
+
+    /// Get the state of an account.
+    pub fn account_state(&self, username: &str) -> Option<AccountState> {
+        self.state().accounts.get(username).map(|a| a.state)
+    }
+
+    /// Get the current password of an account.
+    pub fn password(&self, username: &str) -> Option<String> {
+        self.state()
+            .accounts
+            .get(username)
+            .map(|a| a.password.clone())
+    }
+
+    /// Require a captcha whose answer is `answer` on the login page,
+    /// or stop requiring captcha with [`None`].
+    pub fn require_captcha(&self, answer: Option<&str>) {
+        self.state().captcha = answer.map(|a| a.to_owned());
+    }
+
+    /// Authorize the Wechat login request with `uuid` as `username`.
+    pub fn authorize_wechat(&self, uuid: &str, username: &str) {
+        self.state()
+            .wechat
+            .insert(uuid.to_owned(), username.to_owned());
+    }
+
+    /// Respond `503 Service Unavailable` to the next `count` requests.
+    pub fn fail_next(&self, count: usize) {
+        self.state().failures = count;
+    }
+
+    /// Invalidate all issued tokens, as if they expired.
+    pub fn expire_tokens(&self) {
+        let mut state = self.state();
+        state.tokens.clear();
+        state.webvpn_tickets.values_mut().for_each(|u| *u = None);
+    }
+
+    /// Get the number of requests received.
+    pub fn request_count(&self) -> usize {
+        self.state().request_count
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Account {
+    password: String,
+    state: AccountState,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    accounts: HashMap<String, Account>,
+    /// Issued login tickets that are not used yet.
+    login_tickets: HashSet<String>,
+    /// Ticket granting tickets and their owners.
+    tokens: HashMap<String, String>,
+    /// Ticket cookies of the WebVPN and the logged-in users.
+    webvpn_tickets: HashMap<String, Option<String>>,
+    /// Service tickets, their services and owners.
+    service_tickets: HashMap<String, (String, String)>,
+    /// Authorized uuid of Wechat login requests and their users.
+    wechat: HashMap<String, String>,
+    captcha: Option<String>,
+    failures: usize,
+    request_count: usize,
+    serial: u64,
+}
+
+/// Which CAS a request is sent to.
+#[derive(Debug, Clone, Copy)]
+enum Site {
+    Direct,
+    WebVpn,
+}
+
+impl Site {
+    fn prefix(self) -> &'static str {
+        match self {
+            Site::Direct => "/tpass/",
+            Site::WebVpn => "/webvpn/tpass/",
+        }
+    }
+}
+
+/// A request with the whole body read.
+struct Incoming {
+    method: Method,
+    path: String,
+    query: HashMap<String, String>,
+    cookies: HashMap<String, String>,
+    form: HashMap<String, String>,
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+
+    let mut incoming = Incoming {
+        method: parts.method,
+        path: parts.uri.path().to_owned(),
+        query: parse_pairs(parts.uri.query().unwrap_or_default().as_bytes()),
+        cookies: parts
+            .headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|kv| kv.trim().split_once('='))
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect(),
+        form: parse_pairs(&body),
+    };
+
+    Ok(state.lock().unwrap().route(&mut incoming))
+}
+
+fn parse_pairs(raw: &[u8]) -> HashMap<String, String> {
+    form_urlencoded::parse(raw).into_owned().collect()
+}
+
+impl State {
+    fn route(&mut self, request: &mut Incoming) -> Response<Body> {
+        self.request_count += 1;
+
+        if self.failures > 0 {
+            self.failures -= 1;
+            return text(StatusCode::SERVICE_UNAVAILABLE, "service unavailable");
+        }
+
+        let path = request.path.clone();
+        let (site, page) = if let Some(page) = path.strip_prefix(Site::Direct.prefix()) {
+            (Site::Direct, page)
+        } else if let Some(page) = path.strip_prefix(Site::WebVpn.prefix()) {
+            (Site::WebVpn, page)
+        } else if path == "/webvpn/logout" {
+            (Site::WebVpn, "logout")
+        } else {
+            return text(StatusCode::NOT_FOUND, "not found");
+        };
+
+        // the WebVPN issues a ticket cookie to every visitor before login
+        let new_ticket = match site {
+            Site::WebVpn if !request.cookies.contains_key(WEBVPN_COOKIE_NAME) => {
+                let ticket = self.next_id("");
+                self.webvpn_tickets.insert(ticket.clone(), None);
+                request
+                    .cookies
+                    .insert(WEBVPN_COOKIE_NAME.to_owned(), ticket.clone());
+                Some(ticket)
+            }
+            _ => None,
+        };
+        let request = &*request;
+
+        let mut response = match (&request.method, page) {
+            (&Method::GET, "login") => self.login_page(site, request),
+            (&Method::POST, "login") => self.login(site, request),
+            (&Method::GET, "logout") => self.logout(site, request),
+            (&Method::GET, "code") => image(),
+            (&Method::GET, "serviceValidate") => self.service_validate(request),
+            (&Method::GET, "checkQRCodeScan") => self.check_qrcode_scan(site, request),
+            (&Method::POST, "resetPassword") => self.reset_password(site, request),
+            _ => text(StatusCode::NOT_FOUND, "not found"),
+        };
+
+        if let Some(ticket) = new_ticket {
+            set_cookie(
+                &mut response,
+                &format!("{}={}; Path=/", WEBVPN_COOKIE_NAME, ticket),
+            );
+        }
+
+        response
+    }
+
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.serial += 1;
+        format!("{}{:016x}", prefix, self.serial)
+    }
+
+    /// Get the user logged in via `site`.
+    fn current_user(&self, site: Site, request: &Incoming) -> Option<String> {
+        match site {
+            Site::Direct => request
+                .cookies
+                .get("CASTGC")
+                .and_then(|t| self.tokens.get(t))
+                .cloned(),
+            Site::WebVpn => request
+                .cookies
+                .get(WEBVPN_COOKIE_NAME)
+                .and_then(|t| self.webvpn_tickets.get(t).cloned().flatten()),
+        }
+    }
+
+    fn login_page(&mut self, site: Site, request: &Incoming) -> Response<Body> {
+        let username = match self.current_user(site, request) {
+            Some(username) => username,
+            None => return self.render_login(site, None),
+        };
+
+        if let Some(service) = request.query.get("service") {
+            return self.issue_service_ticket(service, &username);
+        }
+
+        let state = self.accounts.get(&username).map(|a| a.state);
+        match state {
+            Some(AccountState::NeedReset) => render_reset(site, None),
+            Some(AccountState::Banned) => html(
+                "<html><head><title>系统提示</title></head><body>账号已被禁用</body></html>"
+                    .to_owned(),
+            ),
+            _ => html(format!(
+                r#"<html><head><title>东北大学-个人门户</title></head>
+<script>var id_number = "{}";</script></html>"#,
+                username
+            )),
+        }
+    }
+
+    fn render_login(&mut self, site: Site, error: Option<&str>) -> Response<Body> {
+        let lt = self.next_id("LT-");
+        let lt = format!("{}-tpass", lt);
+        self.login_tickets.insert(lt.clone());
+
+        let captcha = match self.captcha {
+            Some(_) => format!(
+                r#"<input type="text" id="code" name="code"/><img id="codeImage" src="{}code"/>"#,
+                site.prefix()
+            ),
+            None => "".to_owned(),
+        };
+
+        html(format!(
+            r#"<html><head><title>智慧东大--统一身份认证</title></head><body>
+<form id="loginForm" action="{prefix}login" method="post">
+<input type="text" id="un" name="un"/>
+<input type="password" id="pd" name="pd"/>
+{captcha}
+<input type="hidden" id="rsa" name="rsa"/>
+<input type="hidden" id="ul" name="ul"/>
+<input type="hidden" id="pl" name="pl"/>
+<input type="hidden" id="lt" name="lt" value="{lt}"/>
+<input type="hidden" name="execution" value="e1s1"/>
+<input type="hidden" name="_eventId" value="submit"/>
+</form>
+<span id="errormsg" class="errormsg">{error}</span>
+</body></html>"#,
+            prefix = site.prefix(),
+            captcha = captcha,
+            lt = lt,
+            error = error.unwrap_or_default(),
+        ))
+    }
+
+    fn login(&mut self, site: Site, request: &Incoming) -> Response<Body> {
+        let field = |name: &str| request.form.get(name).map(|s| s.as_str()).unwrap_or("");
+
+        let lt = field("lt");
+        if !self.login_tickets.remove(lt) {
+            return self.render_login(site, Some("页面已过期，请刷新后重试"));
+        }
+
+        if let Some(answer) = &self.captcha {
+            if field("code") != answer {
+                return self.render_login(site, Some("验证码错误"));
+            }
+        }
+
+        let rsa = field("rsa");
+        let username = self
+            .accounts
+            .iter()
+            .find(|(username, account)| is_valid_rsa(rsa, username, &account.password, lt))
+            .map(|(username, account)| (username.clone(), account.state));
+
+        let username = match username {
+            Some((_, AccountState::Locked)) => {
+                return self.render_login(site, Some("账号已被锁定，请稍后再试"))
+            }
+            Some((username, _)) => username,
+            None => return self.render_login(site, Some("用户名或密码错误")),
+        };
+
+        let mut response = match request.query.get("service") {
+            Some(service) => self.issue_service_ticket(service, &username),
+            None => redirect(&format!("{}login", site.prefix())),
+        };
+        self.grant(site, request, &username, &mut response);
+
+        response
+    }
+
+    /// Mark the user logged in, setting the auth cookie on `response`.
+    fn grant(
+        &mut self,
+        site: Site,
+        request: &Incoming,
+        username: &str,
+        response: &mut Response<Body>,
+    ) {
+        match site {
+            Site::Direct => {
+                let token = self.next_id("TGT-");
+                let token = format!("{}-{}-tpass", token, username);
+                self.tokens.insert(token.clone(), username.to_owned());
+                set_cookie(response, &format!("CASTGC={}; Path=/tpass/", token));
+            }
+            Site::WebVpn => {
+                if let Some(ticket) = request.cookies.get(WEBVPN_COOKIE_NAME) {
+                    self.webvpn_tickets
+                        .insert(ticket.clone(), Some(username.to_owned()));
+                }
+            }
+        }
+    }
+
+    fn logout(&mut self, site: Site, request: &Incoming) -> Response<Body> {
+        let mut response = html("<html><head><title>注销成功</title></head></html>".to_owned());
+
+        match site {
+            Site::Direct => {
+                if let Some(token) = request.cookies.get("CASTGC") {
+                    self.tokens.remove(token);
+                }
+                set_cookie(&mut response, "CASTGC=; Path=/tpass/; Max-Age=0");
+            }
+            Site::WebVpn => {
+                if let Some(ticket) = request.cookies.get(WEBVPN_COOKIE_NAME) {
+                    self.webvpn_tickets.insert(ticket.clone(), None);
+                }
+            }
+        }
+
+        response
+    }
+
+    fn issue_service_ticket(&mut self, service: &str, username: &str) -> Response<Body> {
+        let ticket = self.next_id("ST-");
+        let ticket = format!("{}-tpass", ticket);
+        self.service_tickets
+            .insert(ticket.clone(), (service.to_owned(), username.to_owned()));
+
+        match Url::parse_with_params(service, &[("ticket", &ticket)]) {
+            Ok(url) => redirect(url.as_str()),
+            Err(_) => text(StatusCode::BAD_REQUEST, "invalid service"),
+        }
+    }
+
+    fn service_validate(&mut self, request: &Incoming) -> Response<Body> {
+        let service = request.query.get("service").cloned().unwrap_or_default();
+        let ticket = request.query.get("ticket").cloned().unwrap_or_default();
+
+        let body = match self.service_tickets.remove(&ticket) {
+            Some((s, username)) if s == service => format!(
+                r#"<cas:serviceResponse xmlns:cas="http://www.yale.edu/tp/cas">
+    <cas:authenticationSuccess>
+        <cas:user>{}</cas:user>
+    </cas:authenticationSuccess>
+</cas:serviceResponse>"#,
+                username
+            ),
+            _ => format!(
+                r#"<cas:serviceResponse xmlns:cas="http://www.yale.edu/tp/cas">
+    <cas:authenticationFailure code="INVALID_TICKET">
+        Ticket {} not recognized
+    </cas:authenticationFailure>
+</cas:serviceResponse>"#,
+                ticket
+            ),
+        };
+
+        text(StatusCode::OK, &body)
+    }
+
+    fn check_qrcode_scan(&mut self, site: Site, request: &Incoming) -> Response<Body> {
+        let username = request
+            .query
+            .get("uuid")
+            .and_then(|uuid| self.wechat.remove(uuid));
+
+        match username {
+            Some(username) => {
+                let mut response = text(StatusCode::OK, "success");
+                self.grant(site, request, &username, &mut response);
+                response
+            }
+            None => text(StatusCode::OK, ""),
+        }
+    }
+
+    fn reset_password(&mut self, site: Site, request: &Incoming) -> Response<Body> {
+        let username = match self.current_user(site, request) {
+            Some(username) => username,
+            None => return redirect(&format!("{}login", site.prefix())),
+        };
+
+        let field = |name: &str| request.form.get(name).cloned().unwrap_or_default();
+        let (old, new) = (field("oldPassword"), field("newPassword"));
+
+        if let Some(account) = self.accounts.get_mut(&username) {
+            if account.state == AccountState::NeedReset {
+                if account.password != old {
+                    return render_reset(site, Some("原密码错误"));
+                }
+                if new != field("confirmPassword") {
+                    return render_reset(site, Some("两次输入的密码不一致"));
+                }
+                account.password = new;
+                account.state = AccountState::Active;
+            }
+        }
+
+        redirect(&format!("{}login", site.prefix()))
+    }
+}
+
+fn render_reset(site: Site, error: Option<&str>) -> Response<Body> {
+    html(format!(
+        r#"<html><head><title>智慧东大</title></head><body>
+<form id="resetForm" action="{prefix}resetPassword" method="post">
+<input type="password" name="oldPassword"/>
+<input type="password" name="newPassword"/>
+<input type="password" name="confirmPassword"/>
+</form>
+<span id="errormsg" class="errormsg">{error}</span>
+</body></html>"#,
+        prefix = site.prefix(),
+        error = error.unwrap_or_default(),
+    ))
+}
+
+/// Check `rsa` against the credential, in plaintext or encrypted by `strEnc`.
+fn is_valid_rsa(rsa: &str, username: &str, password: &str, lt: &str) -> bool {
+    let plain = format!("{}{}{}", username, password, lt);
+
+    #[cfg(feature = "des-encrypt")]
+    if rsa == crate::auth::des::str_enc(&plain, &["1", "2", "3"]) {
+        return true;
+    }
+
+    rsa == plain
+}
+
+fn html(body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/html;charset=UTF-8"),
+    );
+    response
+}
+
+fn text(status: StatusCode, body: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(body.to_owned()));
+    *response.status_mut() = status;
+    response
+}
+
+fn image() -> Response<Body> {
+    let mut response = Response::new(Body::from(vec![0xff, 0xd8, 0xff, 0xd9]));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("image/jpeg"));
+    response
+}
+
+fn redirect(location: &str) -> Response<Body> {
+    let mut response = text(StatusCode::FOUND, "");
+    if let Ok(location) = HeaderValue::from_str(location) {
+        response.headers_mut().insert(LOCATION, location);
+    }
+    response
+}
+
+fn set_cookie(response: &mut Response<Body>, cookie: &str) {
+    if let Ok(cookie) = HeaderValue::from_str(cookie) {
+        response.headers_mut().append(SET_COOKIE, cookie);
+    }
+}
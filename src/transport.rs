@@ -0,0 +1,372 @@
+//! The HTTP layer under the asynchronous and the blocking `Session`, so that auth flows
+//! can be exercised against a scripted transport without the real CAS.
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use reqwest::{
+    header::{HeaderMap, LOCATION},
+    redirect::Policy,
+    Client, Method, ResponseBuilderExt, StatusCode, Url,
+};
+
+use crate::error::Result;
+
+/// A request sent by auth flows through [`Session::execute`](crate::Session::execute).
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub(crate) method: Method,
+    pub(crate) url: Url,
+    pub(crate) form: Option<Vec<(String, String)>>,
+    pub(crate) follow_redirects: bool,
+}
+
+impl HttpRequest {
+    /// Creates a `GET` request to `url`.
+    pub fn get(url: Url) -> Self {
+        HttpRequest {
+            method: Method::GET,
+            url,
+            form: None,
+            follow_redirects: true,
+        }
+    }
+
+    /// Creates a `POST` request to `url`, with `form` as the urlencoded body.
+    pub fn post_form(url: Url, form: Vec<(String, String)>) -> Self {
+        HttpRequest {
+            method: Method::POST,
+            url,
+            form: Some(form),
+            follow_redirects: true,
+        }
+    }
+
+    /// Return the redirect response itself instead of following it.
+    pub fn no_redirect(mut self) -> Self {
+        self.follow_redirects = false;
+        self
+    }
+}
+
+/// A response with the whole body read.
+#[derive(Debug)]
+pub struct HttpResponse {
+    /// The final url after redirects.
+    pub(crate) url: Url,
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Get the final url after redirects.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Get the status.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get the body.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Get the body as text, replacing invalid UTF-8 sequences.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Get the absolute url in the `Location` header.
+    pub(crate) fn location(&self) -> Option<Url> {
+        self.headers
+            .get(LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| self.url.join(l).ok())
+    }
+
+    pub(crate) fn into_reqwest(self) -> reqwest::Response {
+        let mut builder = http::Response::builder().status(self.status).url(self.url);
+        if let Some(headers) = builder.headers_mut() {
+            *headers = self.headers;
+        }
+        builder
+            .body(self.body)
+            .expect("status and headers are already valid")
+            .into()
+    }
+}
+
+/// Sends [`HttpRequest`]s, with cookies managed by the cookie jar of the session.
+#[async_trait]
+pub(crate) trait Transport: Debug + Send + Sync {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+/// The fragment marking requests whose redirects are returned instead of followed,
+/// since one redirect policy is shared by all requests of a client.
+///
+/// Fragments are never sent to servers.
+static NO_REDIRECT_FRAGMENT: &str = "neust-no-redirect";
+
+/// The redirect policy of clients in sessions, following up to 10 redirects
+/// except for requests marked by [`NO_REDIRECT_FRAGMENT`].
+pub(crate) fn redirect_policy() -> Policy {
+    Policy::custom(|attempt| {
+        let first = attempt.previous().first().and_then(|url| url.fragment());
+        if first == Some(NO_REDIRECT_FRAGMENT) {
+            attempt.stop()
+        } else if attempt.previous().len() > 10 {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// The default [`Transport`] backed by reqwest.
+#[derive(Debug)]
+pub(crate) struct ReqwestTransport {
+    /// A client using [`redirect_policy`].
+    pub(crate) client: Client,
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut url = request.url.clone();
+        if !request.follow_redirects {
+            url.set_fragment(Some(NO_REDIRECT_FRAGMENT));
+        }
+
+        let mut builder = self.client.request(request.method, url);
+        if let Some(form) = &request.form {
+            builder = builder.form(form);
+        }
+
+        let response = builder.send().await?;
+
+        Ok(HttpResponse {
+            url: match request.follow_redirects {
+                true => response.url().clone(),
+                false => request.url,
+            },
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.bytes().await?.to_vec(),
+        })
+    }
+}
+
+/// Sends [`HttpRequest`]s for the blocking `Session`, the blocking counterpart of [`Transport`].
+#[cfg(feature = "blocking")]
+pub(crate) trait BlockingTransport: Debug + Send + Sync {
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+/// The default [`BlockingTransport`] backed by reqwest.
+#[cfg(feature = "blocking")]
+#[derive(Debug)]
+pub(crate) struct ReqwestBlockingTransport {
+    /// A client using [`redirect_policy`].
+    pub(crate) client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingTransport for ReqwestBlockingTransport {
+    fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut url = request.url.clone();
+        if !request.follow_redirects {
+            url.set_fragment(Some(NO_REDIRECT_FRAGMENT));
+        }
+
+        let mut builder = self.client.request(request.method, url);
+        if let Some(form) = &request.form {
+            builder = builder.form(form);
+        }
+
+        let response = builder.send()?;
+
+        Ok(HttpResponse {
+            url: match request.follow_redirects {
+                true => response.url().clone(),
+                false => request.url,
+            },
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.bytes()?.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use reqwest::{
+        cookie::CookieStore,
+        header::{HeaderMap, HeaderName, HeaderValue, LOCATION, SET_COOKIE},
+        Method, StatusCode,
+    };
+
+    use crate::error::Result;
+    use crate::jar::CookieJar;
+    use crate::transport::{HttpRequest, HttpResponse, Transport};
+
+    /// A [`Transport`] answering requests with scripted responses in order.
+    ///
+    /// It panics when a request does not match the next expected one,
+    /// so that tests fail on unexpected flows.
+    #[derive(Debug, Default)]
+    pub(crate) struct MockTransport {
+        cookie_jar: Arc<CookieJar>,
+        script: Mutex<VecDeque<(Method, String, MockResponse)>>,
+        requests: Mutex<Vec<HttpRequest>>,
+        cookies: Mutex<Vec<Option<String>>>,
+    }
+
+    /// A scripted response.
+    #[derive(Debug, Clone)]
+    pub(crate) struct MockResponse {
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Vec<u8>,
+    }
+
+    impl MockResponse {
+        pub(crate) fn ok(body: impl Into<Vec<u8>>) -> Self {
+            MockResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: body.into(),
+            }
+        }
+
+        pub(crate) fn redirect(location: &str) -> Self {
+            MockResponse::ok("")
+                .status(StatusCode::FOUND)
+                .header(LOCATION, location)
+        }
+
+        pub(crate) fn status(mut self, status: StatusCode) -> Self {
+            self.status = status;
+            self
+        }
+
+        pub(crate) fn header(mut self, name: HeaderName, value: &str) -> Self {
+            self.headers
+                .append(name, HeaderValue::from_str(value).unwrap());
+            self
+        }
+    }
+
+    impl MockTransport {
+        pub(crate) fn new() -> Self {
+            MockTransport::default()
+        }
+
+        /// Expect a request with `method` to a url starting with `url`, answered by `response`.
+        pub(crate) fn expect(self, method: Method, url: &str, response: MockResponse) -> Self {
+            self.script
+                .lock()
+                .unwrap()
+                .push_back((method, url.to_owned(), response));
+            self
+        }
+
+        pub(crate) fn cookie_jar(&self) -> Arc<CookieJar> {
+            self.cookie_jar.clone()
+        }
+
+        /// Get requests received, including those sent when following redirects.
+        pub(crate) fn requests(&self) -> Vec<HttpRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        /// Get the `Cookie` header sent with each request.
+        pub(crate) fn cookies(&self) -> Vec<Option<String>> {
+            self.cookies.lock().unwrap().clone()
+        }
+
+        /// Assert that all scripted responses are consumed.
+        pub(crate) fn assert_done(&self) {
+            let script = self.script.lock().unwrap();
+            assert!(script.is_empty(), "unconsumed responses: {:?}", script);
+        }
+
+        fn respond(&self, request: &HttpRequest) -> HttpResponse {
+            let (method, url, response) = self
+                .script
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| panic!("unexpected request: {:?}", request));
+
+            assert_eq!(request.method, method, "unexpected request: {:?}", request);
+            assert!(
+                request.url.as_str().starts_with(&url),
+                "unexpected request: {:?}, expect {}",
+                request,
+                url
+            );
+
+            self.requests.lock().unwrap().push(request.clone());
+            self.cookies.lock().unwrap().push(
+                self.cookie_jar
+                    .cookies(&request.url)
+                    .and_then(|c| c.to_str().map(|s| s.to_owned()).ok()),
+            );
+            self.cookie_jar.set_cookies(
+                &mut response.headers.get_all(SET_COOKIE).iter(),
+                &request.url,
+            );
+
+            HttpResponse {
+                url: request.url.clone(),
+                status: response.status,
+                headers: response.headers,
+                body: response.body,
+            }
+        }
+
+        /// Respond to `request`, following redirects if asked.
+        fn run(&self, mut request: HttpRequest) -> HttpResponse {
+            loop {
+                let response = self.respond(&request);
+
+                match response.location() {
+                    Some(location)
+                        if request.follow_redirects && response.status.is_redirection() =>
+                    {
+                        request = HttpRequest::get(location);
+                    }
+                    _ => return response,
+                }
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Transport for MockTransport {
+        async fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            Ok(self.run(request))
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    impl crate::transport::BlockingTransport for MockTransport {
+        fn execute(&self, request: HttpRequest) -> Result<HttpResponse> {
+            Ok(self.run(request))
+        }
+    }
+}
@@ -1,7 +1,5 @@
 #![allow(dead_code)]
 
-use tokio;
-
 use neust::auth::Credential;
 use neust::Result;
 use neust::UserStatus;
@@ -22,6 +20,52 @@ async fn test_login() {
     assert_eq!(token_a, token_c);
 }
 
+#[ignore]
+#[tokio::test]
+async fn test_relogin() {
+    let session = Session::new();
+    let auth = get_credential();
+    extract_token(session.login(&auth).await);
+    let token_a = extract_token(session.relogin(&auth).await);
+    let token_b = extract_token(session.check_status().await);
+    assert_eq!(token_a, token_b);
+}
+
+#[ignore]
+#[tokio::test]
+async fn test_logout() {
+    let session = Session::new();
+    let auth = get_credential();
+    extract_token(session.login(&auth).await);
+
+    session.logout().await.expect("fail to logout");
+    assert!(session.check_status().await.unwrap().is_rejected());
+}
+
+#[ignore]
+#[tokio::test]
+async fn test_session_manager() {
+    let manager = neust::SessionManager::new(Session::new(), get_credential());
+    let session = manager.ensure_active().await.expect("fail to login");
+    let token_a = extract_token(session.check_status().await);
+
+    session.logout().await.expect("fail to logout");
+    let session = manager.ensure_active().await.expect("fail to login again");
+    let token_b = extract_token(session.check_status().await);
+    assert_ne!(token_a, token_b);
+}
+
+#[cfg(feature = "blocking")]
+#[ignore]
+#[test]
+fn test_blocking_login() {
+    let session = neust::blocking::Session::new();
+    let auth = get_credential();
+    let token_a = extract_token(session.login(&auth));
+    let token_b = extract_token(session.check_status());
+    assert_eq!(token_a, token_b);
+}
+
 #[cfg(feature = "webvpn")]
 #[ignore]
 #[tokio::test]
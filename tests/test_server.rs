@@ -0,0 +1,394 @@
+#![cfg(feature = "test-server")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use neust::auth::{CaptchaChallenge, Credential, Token};
+use neust::reqwest::StatusCode;
+use neust::testing::{AccountState, TestServer};
+use neust::{cas, Endpoint, Error, RateLimiter, RejectReason, RetryPolicy, Session, UserStatus};
+
+async fn start_server() -> TestServer {
+    let server = TestServer::start()
+        .await
+        .expect("fail to start test server");
+    server.add_account("20180000", "password");
+    server
+}
+
+#[tokio::test]
+async fn test_login() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+
+    let session = Session::new();
+    let status = session
+        .login_at(&endpoint, &Credential::new("20180000", "password"))
+        .await
+        .unwrap();
+    assert_eq!(status.get_username(), Some("20180000"));
+    let token = status.get_token().unwrap().to_owned();
+
+    let status = session.check_status_at(&endpoint).await.unwrap();
+    assert_eq!(status.get_token(), Some(token.as_str()));
+
+    let session = Session::new();
+    let status = session
+        .login_at(&endpoint, &Token::new(token.clone()))
+        .await
+        .unwrap();
+    assert_eq!(status.get_username(), Some("20180000"));
+
+    session.logout_at(&endpoint).await.unwrap();
+
+    let session = Session::new();
+    let status = session
+        .login_at(&endpoint, &Token::new(token))
+        .await
+        .unwrap();
+    assert_eq!(
+        status.get_reject_reason(),
+        Some(&RejectReason::TokenExpired)
+    );
+}
+
+#[tokio::test]
+async fn test_login_denied() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+
+    let session = Session::new();
+    let status = session
+        .login_at(&endpoint, &Credential::new("20180000", "wrong"))
+        .await
+        .unwrap();
+    assert_eq!(
+        status.get_reject_reason(),
+        Some(&RejectReason::WrongCredentials)
+    );
+
+    server.set_account_state("20180000", AccountState::Locked);
+    let status = session
+        .login_at(&endpoint, &Credential::new("20180000", "password"))
+        .await
+        .unwrap();
+    assert_eq!(
+        status.get_reject_reason(),
+        Some(&RejectReason::AccountLocked)
+    );
+
+    server.set_account_state("20180000", AccountState::Banned);
+    let status = session
+        .login_at(&endpoint, &Credential::new("20180000", "password"))
+        .await
+        .unwrap();
+    assert!(matches!(status, UserStatus::Banned { .. }));
+}
+
+#[tokio::test]
+async fn test_login_captcha() {
+    let server = start_server().await;
+    server.require_captcha(Some("a1b2"));
+    let endpoint = server.endpoint();
+
+    let session = Session::new();
+    let status = session
+        .login_at(&endpoint, &Credential::new("20180000", "password"))
+        .await
+        .unwrap();
+    let challenge = match status.get_reject_reason() {
+        Some(RejectReason::CaptchaRequired {
+            challenge: Some(challenge),
+        }) => challenge.clone(),
+        other => panic!("unexpected reason: {:?}", other),
+    };
+    assert_eq!(challenge.content_type(), Some("image/jpeg"));
+
+    let credential = Credential::new("20180000", "password").with_captcha_answer("a1b2");
+    let status = session.login_at(&endpoint, &credential).await.unwrap();
+    assert!(status.is_active());
+
+    let session = Session::new();
+    let credential = Credential::new("20180000", "password").with_captcha_solver(
+        |challenge: CaptchaChallenge| async move {
+            assert_eq!(challenge.content_type(), Some("image/jpeg"));
+            Some("a1b2".to_owned())
+        },
+    );
+    let status = session.login_at(&endpoint, &credential).await.unwrap();
+    assert!(status.is_active());
+}
+
+#[tokio::test]
+async fn test_reset_password() {
+    let server = start_server().await;
+    server.set_account_state("20180000", AccountState::NeedReset);
+    let endpoint = server.endpoint();
+
+    let session = Session::new();
+    let status = session
+        .login_at(&endpoint, &Credential::new("20180000", "password"))
+        .await
+        .unwrap();
+    assert!(matches!(status, UserStatus::NeedReset { .. }));
+
+    let policy = Default::default();
+    match session
+        .reset_password_at(&endpoint, &policy, "wrong", "Neu@2023")
+        .await
+    {
+        Err(Error::ResetRejected { message }) => assert_eq!(message, "原密码错误"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(server.password("20180000").as_deref(), Some("password"));
+    assert_eq!(
+        server.account_state("20180000"),
+        Some(AccountState::NeedReset)
+    );
+
+    let status = session
+        .reset_password_at(&endpoint, &policy, "password", "Neu@2023")
+        .await
+        .unwrap();
+    assert!(status.is_active());
+    assert_eq!(server.password("20180000").as_deref(), Some("Neu@2023"));
+    assert_eq!(server.account_state("20180000"), Some(AccountState::Active));
+}
+
+#[tokio::test]
+async fn test_service_ticket() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+
+    let session = Session::new();
+    assert!(matches!(
+        session
+            .service_ticket_at(&endpoint, "https://example.com/")
+            .await,
+        Err(Error::StatusConflict)
+    ));
+
+    session
+        .login_at(&endpoint, &Credential::new("20180000", "password"))
+        .await
+        .unwrap();
+    let ticket = session
+        .service_ticket_at(&endpoint, "https://example.com/")
+        .await
+        .unwrap();
+
+    let client = neust::reqwest::Client::new();
+    let validation = cas::validate_at(&client, &endpoint, "https://example.com/", &ticket)
+        .await
+        .unwrap();
+    assert_eq!(validation.username, "20180000");
+
+    assert!(matches!(
+        cas::validate_at(&client, &endpoint, "https://example.com/", &ticket).await,
+        Err(Error::TicketValidationError { .. })
+    ));
+
+    let endpoint = Endpoint::builder(format!("{}tpass/", server.url()))
+        .service_validate_url(format!("{}tpass/missing", server.url()))
+        .build()
+        .unwrap();
+    assert!(matches!(
+        cas::validate_at(&client, &endpoint, "https://example.com/", &ticket).await,
+        Err(Error::HttpStatus {
+            status: StatusCode::NOT_FOUND,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_login_with_service() {
+    let server = start_server().await;
+    let login_url = server.endpoint().login_url().to_owned();
+
+    let client = neust::reqwest::Client::builder()
+        .redirect(neust::reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let html = client
+        .get(&login_url)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let lt = html
+        .split(r#"name="lt" value=""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .unwrap();
+
+    let rsa = format!("20180000password{}", lt);
+    let response = client
+        .post(&login_url)
+        .query(&[("service", "https://example.com/")])
+        .form(&[("rsa", rsa.as_str()), ("lt", lt)])
+        .send()
+        .await
+        .unwrap();
+
+    // the service ticket is issued along with the auth cookie.
+    let location = response.headers()["location"].to_str().unwrap();
+    assert!(location.starts_with("https://example.com/?ticket=ST-"));
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.starts_with("CASTGC=TGT-"));
+}
+
+#[tokio::test]
+async fn test_webvpn() {
+    let server = start_server().await;
+    let endpoint = server.webvpn_endpoint();
+
+    let session = Session::new();
+    let credential = Credential::new("20180000", "password");
+    let status = session.login_at(&endpoint, &credential).await.unwrap();
+    assert_eq!(status.get_username(), Some("20180000"));
+
+    server.expire_tokens();
+    assert!(session
+        .check_status_at(&endpoint)
+        .await
+        .unwrap()
+        .is_rejected());
+
+    let status = session.relogin_at(&endpoint, &credential).await.unwrap();
+    assert!(status.is_active());
+
+    session.logout_at(&endpoint).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_failures() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+    server.fail_next(1);
+
+    let session = Session::new();
+    let credential = Credential::new("20180000", "password");
+    assert!(session.login_at(&endpoint, &credential).await.is_err());
+
+    let status = session.login_at(&endpoint, &credential).await.unwrap();
+    assert!(status.is_active());
+    assert!(server.request_count() >= 5);
+}
+
+#[tokio::test]
+async fn test_retry() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+    server.fail_next(2);
+
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(10),
+        ..RetryPolicy::default()
+    };
+    let session = Session::new().with_retry_policy(policy);
+    let credential = Credential::new("20180000", "password");
+    let status = session.login_at(&endpoint, &credential).await.unwrap();
+    assert!(status.is_active());
+
+    server.fail_next(3);
+    match session.check_status_at(&endpoint).await {
+        Err(e) => assert!(e.is_retryable()),
+        Ok(status) => panic!("unexpected status: {}", status),
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+    let host = server.url().host_str().unwrap().to_owned();
+
+    let limiter = Arc::new(RateLimiter::new(2, Duration::from_millis(20)).with_hosts([host]));
+    let sessions: Vec<Session> = (0..3)
+        .map(|_| Session::new().with_rate_limiter(limiter.clone()))
+        .collect();
+
+    let credential = Credential::new("20180000", "password");
+    for session in &sessions {
+        let status = session.login_at(&endpoint, &credential).await.unwrap();
+        assert!(status.is_active());
+    }
+
+    let metrics = limiter.metrics();
+    assert!(metrics.requests >= 9);
+    assert!(metrics.delayed > 0);
+    assert!(metrics.total_wait >= metrics.max_wait);
+}
+
+#[cfg(feature = "wechat")]
+#[tokio::test]
+async fn test_wechat() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+    let wechat = neust::auth::Wechat::new(Some("uuid".to_owned()));
+
+    let session = Session::new();
+    let status = session.login_at(&endpoint, &wechat).await.unwrap();
+    assert_eq!(
+        status.get_reject_reason(),
+        Some(&RejectReason::PendingWechatAuthorization)
+    );
+
+    server.authorize_wechat("uuid", "20180000");
+    let status = session.login_at(&endpoint, &wechat).await.unwrap();
+    assert_eq!(status.get_username(), Some("20180000"));
+}
+
+#[cfg(feature = "blocking")]
+#[tokio::test]
+async fn test_blocking_login() {
+    let server = start_server().await;
+    let endpoint = server.endpoint();
+
+    let status = tokio::task::spawn_blocking(move || {
+        let session = neust::blocking::Session::new();
+        let status = session.login_at(&endpoint, &Credential::new("20180000", "password"))?;
+        session.logout_at(&endpoint)?;
+        Ok::<_, Error>(status)
+    })
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(status.get_username(), Some("20180000"));
+}
+
+#[cfg(feature = "blocking")]
+#[tokio::test]
+async fn test_blocking_login_rejected() {
+    let server = start_server().await;
+    server.require_captcha(Some("a1b2"));
+    let endpoint = server.endpoint();
+
+    let (captcha, wrong, answered) = tokio::task::spawn_blocking(move || {
+        let session = neust::blocking::Session::new();
+        let credential = Credential::new("20180000", "password");
+        let captcha = session.login_at(&endpoint, &credential)?;
+        let wrong = session.login_at(
+            &endpoint,
+            &Credential::new("20180000", "wrong").with_captcha_answer("a1b2"),
+        )?;
+        let answered = session.login_at(&endpoint, &credential.with_captcha_answer("a1b2"))?;
+        Ok::<_, Error>((captcha, wrong, answered))
+    })
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(
+        wrong.get_reject_reason(),
+        Some(&RejectReason::WrongCredentials)
+    );
+    assert!(matches!(
+        captcha.get_reject_reason(),
+        Some(RejectReason::CaptchaRequired { challenge: Some(_) })
+    ));
+    assert!(answered.is_active());
+}